@@ -0,0 +1,25 @@
+//! Exposes the locked `leftwm-core` version as `LEFTWM_CORE_VERSION`, used as a
+//! last resort when the installed `LeftWM` cannot be asked for its version.
+use std::fs;
+
+fn main() {
+    println!("cargo:rerun-if-changed=Cargo.lock");
+    let Ok(lock) = fs::read_to_string("Cargo.lock") else {
+        return;
+    };
+    let mut lines = lock.lines();
+    while let Some(line) = lines.next() {
+        if line.trim() == r#"name = "leftwm-core""# {
+            if let Some(version) = lines
+                .next()
+                .and_then(|l| l.trim().strip_prefix("version = "))
+            {
+                println!(
+                    "cargo:rustc-env=LEFTWM_CORE_VERSION={}",
+                    version.trim_matches('"')
+                );
+            }
+            return;
+        }
+    }
+}
@@ -31,6 +31,7 @@ pub enum LeftErrorKind {
     SemVerError(semver::Error),
     UrlParseError(url::ParseError),
     RonError(ron::Error),
+    RonParse(ron::error::SpannedError),
 }
 
 impl fmt::Display for LeftError {
@@ -58,6 +59,7 @@ impl fmt::Display for LeftErrorKind {
             LeftErrorKind::SemVerError(ref err) => write!(f, "{err}"),
             LeftErrorKind::UrlParseError(ref err) => write!(f, "{err}"),
             LeftErrorKind::RonError(ref err) => write!(f, "{err}"),
+            LeftErrorKind::RonParse(ref err) => write!(f, "{err}"),
         }
     }
 }
@@ -139,3 +141,9 @@ impl From<ron::Error> for LeftError {
         LeftErrorKind::RonError(inner).into()
     }
 }
+
+impl From<ron::error::SpannedError> for LeftError {
+    fn from(inner: ron::error::SpannedError) -> LeftError {
+        LeftErrorKind::RonParse(inner).into()
+    }
+}
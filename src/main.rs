@@ -23,16 +23,17 @@ pub mod operations;
 pub mod utils;
 
 use colored::Colorize;
-use errors::{LeftErrorKind, Result};
+use errors::{LeftError, LeftErrorKind, Result};
 
-use crate::models::Config;
+use crate::models::{Config, LeftWm};
 use crate::operations::{
-    Apply, Current, Install, List, Migrate, New, Search, Status, Support, Uninstall, Update,
-    Upgrade,
+    Adopt, Apply, Current, Fork, Index, Install, Lint, List, Lock, Migrate, New, Palette,
+    PublishEntry, Recolor, Repair, Search, Status, Support, SyncThemes, Uninstall, Update, Upgrade,
 };
 use clap::Parser;
-use log::error;
+use log::{error, warn};
 use std::env;
+use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
 #[clap(author, about, version)]
@@ -40,6 +41,12 @@ pub struct Opt {
     /// Verbose mode (-v, -vv, -vvv, etc.)
     #[clap(short, long, action = clap::ArgAction::Count)]
     pub verbose: u8,
+    /// Assume this leftwm version instead of asking the installed leftwm
+    #[clap(long, global = true, env = "LEFTWM_VERSION")]
+    pub leftwm_version: Option<String>,
+    /// Use this directory instead of ~/.config/leftwm for themes.toml and the themes
+    #[clap(long, global = true, env = "LEFTWM_CONFIG_DIR")]
+    pub config_dir: Option<PathBuf>,
     /// Operation to be performed by the theme manager
     #[clap(subcommand)]
     pub operation: Operation,
@@ -53,17 +60,34 @@ pub enum Operation {
     Install(Install),
     /// Uninstall a theme
     Uninstall(Uninstall),
+    /// Keep an installed theme removed from its repository as a local theme
+    Adopt(Adopt),
     /// List installed theme(s)
     #[clap(name = "list")]
     List(List),
-    /// Migrate `theme.toml` to `theme.ron`
+    /// Check that a theme is well-formed before publishing it
+    Lint(Lint),
+    /// Print the known.toml entry for a theme repository
+    PublishEntry(PublishEntry),
+    /// Tools for maintainers of a theme index (known.toml)
+    Index(Index),
+    /// Migrate `theme.toml` to `theme.ron`, or back
     Migrate(Migrate),
     /// Create new theme
     New(New),
+    /// Copy an installed theme into a new local theme to edit
+    Fork(Fork),
     /// Update installed themes
     Upgrade(Upgrade),
     /// Update theme list
     Update(Update),
+    /// Recreate themes.toml, backing up the old one, and re-import installed themes
+    Repair(Repair),
+    /// Write the installed themes and their commits to a lock file
+    Lock(Lock),
+    /// Make installed themes match themes-wanted.toml or a lock file (see --prune, --dry-run)
+    #[clap(name = "sync")]
+    Sync(SyncThemes),
     /// Apply an already installed theme
     Apply(Apply),
     /// Print out current theme information
@@ -72,8 +96,12 @@ pub enum Operation {
     Search(Search),
     /// Get support (xdg-open)
     Support(Support),
-    /// Get a field from the theme.toml
+    /// Get a field from the current theme's theme.ron or theme.toml
     Current(Current),
+    /// List the colours used by a theme
+    Palette(Palette),
+    /// Create a local copy of a theme recoloured with a base16 or pywal palette
+    Recolor(Recolor),
 }
 
 fn main() {
@@ -88,30 +116,77 @@ fn main() {
 
     pretty_env_logger::init();
 
+    if let Some(version) = &opt.leftwm_version {
+        LeftWm::set_version(version);
+    }
+
+    // Held until leftwm-theme exits, so concurrent runs don't overwrite each
+    // other's changes to themes.toml.
+    let _lock = match Config::new(opt.config_dir.clone()).lock() {
+        Ok(lock) => Some(lock),
+        Err(err) => {
+            warn!("Could not lock the config directory: {err}");
+            None
+        }
+    };
     log::trace!("Loading configuration");
-    let mut config = Config::new(None).load().unwrap_or_default();
+    let mut config = match Config::new(opt.config_dir.clone()).load() {
+        Ok(config) => config,
+        // Repairing doesn't need the config it replaces.
+        Err(_) if matches!(opt.operation, Operation::Repair(_)) => {
+            Config::new(opt.config_dir.clone())
+        }
+        Err(e) => exit_with(e),
+    };
 
+    // The checks meant for CI report failure through the exit status.
+    let exit_on_error = matches!(
+        opt.operation,
+        Operation::Lint(_) | Operation::PublishEntry(_) | Operation::Index(_)
+    );
     let wrapper: Result<()> = match opt.operation {
         //Operation::AutoFind(args) => AutoFind::exec(&args),
         Operation::Install(args) => Install::exec(&args, &mut config),
         Operation::Uninstall(args) => Uninstall::exec(&args, &mut config),
+        Operation::Adopt(args) => Adopt::exec(&args, &mut config),
         Operation::List(args) => List::exec(&args, &mut config),
         Operation::Apply(args) => Apply::exec(&args, &mut config),
         Operation::Status(args) => Status::exec(&args, &mut config),
-        Operation::Migrate(args) => Migrate::exec(&args),
+        Operation::Lint(args) => Lint::exec(&args, &mut config),
+        Operation::PublishEntry(args) => PublishEntry::exec(&args, &mut config),
+        Operation::Index(args) => Index::exec(&args, &mut config),
+        Operation::Migrate(args) => Migrate::exec(&args, &mut config),
         Operation::New(args) => New::exec(&args, &mut config),
+        Operation::Fork(args) => Fork::exec(&args, &mut config),
         Operation::Upgrade(args) => Upgrade::exec(&args, &mut config),
         Operation::Update(args) => Update::exec(&args, &mut config),
+        Operation::Repair(args) => Repair::exec(&args, &mut config),
+        Operation::Lock(args) => Lock::exec(&args, &mut config),
+        Operation::Sync(args) => SyncThemes::exec(&args, &mut config),
         Operation::Search(args) => Search::exec(&args, &mut config),
         Operation::Support(args) => Support::exec(&args, &mut config),
         Operation::Current(args) => Current::exec(&args, &mut config),
+        Operation::Palette(args) => Palette::exec(&args, &mut config),
+        Operation::Recolor(args) => Recolor::exec(&args, &mut config),
     };
 
     if let Err(e) = wrapper {
-        if let LeftErrorKind::UserFriendlyError(msg) = e.inner {
-            println!("{}", &msg.bright_red());
-        } else {
-            error!("Operation did not complete successfully");
+        if exit_on_error {
+            exit_with(e);
         }
+        report(e);
     }
 }
+
+fn report(e: LeftError) {
+    if let LeftErrorKind::UserFriendlyError(msg) = e.inner {
+        println!("{}", &msg.bright_red());
+    } else {
+        error!("Operation did not complete successfully: {e}");
+    }
+}
+
+fn exit_with(e: LeftError) -> ! {
+    report(e);
+    std::process::exit(1);
+}
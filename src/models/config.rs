@@ -1,31 +1,46 @@
-use crate::errors;
-use crate::errors::{LeftError, Result};
+use crate::errors::{friendly_message, LeftError, Result};
+use crate::models::definitions;
 use crate::models::theme::{TempThemes, Theme};
+use crate::utils::overrides;
 use colored::Colorize;
-use log::{error, trace};
+use fs2::FileExt;
+use log::trace;
 use std::ffi::OsStr;
 use std::fs;
-use std::fs::File;
+use std::fs::{File, OpenOptions};
 use std::io::Write;
+use std::os::unix;
 use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 use xdg::BaseDirectories;
 
 pub const THEMES_DIR: &str = "themes";
 
 const BASE_DIR_PREFIX: &str = "leftwm";
-const CURRENT_DIR: &str = "current";
-const LOCAL_REPO_NAME: &str = "LOCAL";
+pub const CURRENT_DIR: &str = "current";
+pub const LOCAL_REPO_NAME: &str = "LOCAL";
 const COMMUNITY_REPO_NAME: &str = "community";
 const THEMES_CONFIG_FILENAME: &str = "themes.toml";
-pub const CURRENT_DEFINITIONS_VERSION: i16 = 1;
+const CONFIG_LOCK_FILENAME: &str = ".themes.toml.lock";
+pub const CURRENT_DEFINITIONS_VERSION: i16 = 2;
 
 /// Contains a vector of all global repositories.
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Config {
     pub repos: Vec<Repo>,
+    /// Where themes.toml and the themes live; ~/.config/leftwm if None
+    #[serde(skip)]
     pub config_dir: Option<PathBuf>,
 }
 
+/// An advisory lock on the config directory, held while themes.toml is
+/// loaded, modified and saved so that concurrent runs of leftwm-theme don't
+/// undo each other's changes. Released when dropped.
+#[derive(Debug)]
+pub struct ConfigLock {
+    _file: File,
+}
+
 /// Contains global repository information. Akin to known.toml or themes.toml
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Repo {
@@ -54,7 +69,7 @@ impl Config {
                 "https://raw.githubusercontent.com/leftwm/leftwm-community-themes/master/known.toml",
             ),
             name: String::from(COMMUNITY_REPO_NAME),
-            definitions_version: 1,
+            definitions_version: CURRENT_DEFINITIONS_VERSION,
             themes: Vec::new(),
         };
         let local_repo = Repo {
@@ -76,6 +91,7 @@ impl Config {
     /// Will error if unable to create configuration directory.
     pub fn get_config_dir(&self) -> Result<PathBuf> {
         if let Some(path) = &self.config_dir {
+            fs::create_dir_all(path)?;
             Ok(path.clone())
         } else {
             let path = BaseDirectories::with_prefix(BASE_DIR_PREFIX);
@@ -97,11 +113,72 @@ impl Config {
     pub fn save(config: &Self) -> Result<&Config> {
         let config_filename = config.get_config_dir()?.join(THEMES_CONFIG_FILENAME);
         let toml = toml::to_string(&config)?;
-        let mut file = File::create(config_filename)?;
-        file.write_all(toml.as_bytes())?;
+        write_atomically(&config_filename, toml.as_bytes())?;
         Ok(config)
     }
 
+    /// Copies themes.toml to `themes.toml.<seconds since epoch>.bak`, unless an
+    /// identical backup exists. Returns the backup, or None if there is no
+    /// themes.toml.
+    ///
+    /// # Errors
+    ///
+    /// Will error if themes.toml cannot be read or the backup written.
+    pub fn backup(&self) -> Result<Option<PathBuf>> {
+        let config_dir = self.get_config_dir()?;
+        let config_filename = config_dir.join(THEMES_CONFIG_FILENAME);
+        if !config_filename.exists() {
+            return Ok(None);
+        }
+        let contents = fs::read(&config_filename)?;
+        let prefix = format!("{THEMES_CONFIG_FILENAME}.");
+        for entry in fs::read_dir(&config_dir)? {
+            let path = entry?.path();
+            let is_backup = path.extension().is_some_and(|extension| extension == "bak")
+                && path
+                    .file_name()
+                    .and_then(OsStr::to_str)
+                    .is_some_and(|name| name.starts_with(&prefix));
+            if is_backup && fs::read(&path).is_ok_and(|backup| backup == contents) {
+                return Ok(Some(path));
+            }
+        }
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|since| since.as_secs())
+            .unwrap_or_default();
+        let backup = config_dir.join(format!("{THEMES_CONFIG_FILENAME}.{seconds}.bak"));
+        fs::write(&backup, contents)?;
+        Ok(Some(backup))
+    }
+
+    /// Locks the config directory, waiting for other runs of leftwm-theme to
+    /// release it first.
+    ///
+    /// # Errors
+    ///
+    /// Will error if the config directory cannot be obtained.
+    /// Will error if the lock file cannot be created or locked.
+    pub fn lock(&self) -> Result<ConfigLock> {
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(false)
+            .open(self.get_config_dir()?.join(CONFIG_LOCK_FILENAME))?;
+        match file.try_lock_exclusive() {
+            Ok(()) => {}
+            Err(err) if err.kind() == fs2::lock_contended_error().kind() => {
+                println!(
+                    "{}",
+                    "Waiting for another leftwm-theme to finish . . . ".bright_yellow()
+                );
+                file.lock_exclusive()?;
+            }
+            Err(err) => return Err(err.into()),
+        }
+        Ok(ConfigLock { _file: file })
+    }
+
     pub fn update_or_append(config: &mut Self, theme: &Theme, repo: (&String, &String)) {
         #![allow(clippy::option_if_let_else)]
         if let Some(target_repo) = config.repos.iter_mut().find(|p| repo.1.clone() == p.name) {
@@ -117,6 +194,10 @@ impl Config {
                         .leftwm_versions
                         .clone_from(&theme.leftwm_versions);
                     target_theme.dependencies.clone_from(&theme.dependencies);
+                    target_theme.branch.clone_from(&theme.branch);
+                    target_theme.renamed_from.clone_from(&theme.renamed_from);
+                    target_theme.deprecated.clone_from(&theme.deprecated);
+                    target_theme.replaced_by.clone_from(&theme.replaced_by);
                 }
                 None => {
                     target_repo.themes.push(theme.clone());
@@ -153,25 +234,35 @@ impl Config {
     ///
     /// Will error if `BaseDirectory` not set (no systemd)
     /// Will error if themes.toml doesn't exist
-    /// Will error if themes.toml has invalid content.
+    /// Will error if themes.toml has invalid content; it is backed up first.
     /// Will error if themes.toml cannot be written to.
     pub fn load(&self) -> Result<Config> {
         let config_filename = self.get_config_dir()?.join(THEMES_CONFIG_FILENAME);
         if Path::new(&config_filename).exists() {
-            let contents = fs::read_to_string(config_filename)?;
+            let contents = fs::read_to_string(&config_filename)?;
             trace!("{:?}", &contents);
-            match toml::from_str::<Config>(&contents) {
-                Ok(config) => Ok(config),
+            let parsed = toml::from_str::<toml::Table>(&contents).and_then(|mut table| {
+                definitions::upgrade_config(&mut table);
+                table.try_into::<Config>()
+            });
+            match parsed {
+                Ok(mut config) => {
+                    config.config_dir.clone_from(&self.config_dir);
+                    Ok(config)
+                }
                 Err(err) => {
-                    error!("TOML error: {err:?}");
-                    Err(errors::LeftError::from("TOML Invalid"))
+                    trace!("TOML error: {err:?}");
+                    let backup = self.backup()?.unwrap_or_default();
+                    Err(friendly_message(&format!(
+                        "{} is invalid and was not loaded: {err}\nIt was backed up to {}. Fix it, or run `leftwm-theme repair` to recreate it.",
+                        config_filename.display(),
+                        backup.display()
+                    )))
                 }
             }
         } else {
-            let config = Config::new(None).default();
-            let toml = toml::to_string(&config)?;
-            let mut file = File::create(&config_filename)?;
-            file.write_all(toml.as_bytes())?;
+            let config = Config::new(self.config_dir.clone()).default();
+            Config::save(&config)?;
             Ok(config)
         }
     }
@@ -186,8 +277,15 @@ impl Config {
     pub fn update_local_repo(&mut self) -> Result<()> {
         // Get a list of all the themes in the themes directory.
         let existing_themes = Repo::installed_themes(&self.get_config_dir()?)?;
+        let themes_dir = self.get_config_dir()?.join(THEMES_DIR);
 
         let mut local_themes: Vec<String> = Vec::new();
+        let remote_themes: Vec<String> = self
+            .repos
+            .iter()
+            .filter(|repo| repo.name != LOCAL_REPO_NAME)
+            .flat_map(|repo| repo.themes.iter().map(|theme| theme.name.clone()))
+            .collect();
 
         // Iterate through the existing themes and check if they are from the
         // remote repos. If not, consider the theme to be a local theme.
@@ -196,7 +294,11 @@ impl Config {
             for repo in &self.repos {
                 if repo.name != LOCAL_REPO_NAME {
                     for theme in &repo.themes {
-                        if tt.eq(&theme.name) {
+                        // A theme renamed upstream may still be installed
+                        // under its old name, see `Repo::preview`.
+                        if tt.eq(&theme.name)
+                            || theme.directory.as_deref() == Some(&themes_dir.join(&tt))
+                        {
                             found = true;
                             break;
                         }
@@ -228,6 +330,9 @@ impl Config {
         // Update the local themes in the Config.
         for repo in &mut self.repos {
             if repo.name == LOCAL_REPO_NAME {
+                // Themes now listed by a remote repository belong to it.
+                repo.themes
+                    .retain(|theme| !remote_themes.contains(&theme.name));
                 repo.compare(local_temp_themes, &config_dir)?;
                 break;
             }
@@ -237,32 +342,87 @@ impl Config {
     }
 }
 
+// Writes to a temporary file next to `path`, then renames it into place, so a
+// crash or a concurrent run never leaves `path` truncated. A symlinked file,
+// e.g. from a dotfiles repository, is replaced at its target.
+fn write_atomically(path: &Path, contents: &[u8]) -> Result<()> {
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    let mut file = tempfile::NamedTempFile::new_in(dir)?;
+    if let Ok(metadata) = fs::metadata(&path) {
+        file.as_file().set_permissions(metadata.permissions())?;
+    }
+    file.write_all(contents)?;
+    file.as_file().sync_all()?;
+    file.persist(&path).map_err(|err| err.error)?;
+    Ok(())
+}
+
 impl Repo {
+    /// Updates the repository with the themes of its index. The themes are
+    /// expected to be upgraded to the current definitions version already, see
+    /// `definitions::parse_index`.
+    ///
     /// # Errors
     ///
-    /// Returns an error if the definitions file is OOD.
+    /// Returns an error if the installed themes cannot be listed or renamed.
     pub fn compare(&mut self, theme_wrap: TempThemes, config_dir: &Path) -> Result<&Repo> {
-        if self.definitions_version > CURRENT_DEFINITIONS_VERSION
-            || theme_wrap.definitions_version > CURRENT_DEFINITIONS_VERSION
-        {
-            println!("{}", "========== ERROR ==========".bold().red());
-            println!("REPOSITORY DEFINITION HAS INCREASED.");
-            println!("USUALLY THIS MEANS YOUR LEFTWM-THEME IS OUT OF DATE.");
-            println!("{}", "========== ERROR ==========".bold().red());
-            return Err(errors::LeftError::from("Definitions file out of date."));
-        }
+        self.merge_index(theme_wrap, config_dir, true)?;
+        Ok(self)
+    }
+
+    /// Like `compare`, but leaves the installed themes alone: a theme renamed
+    /// upstream keeps its directory under the old name. Returns the old and new
+    /// names of such themes, for `rename_installed` to move later.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the installed themes cannot be listed.
+    pub fn preview(
+        &mut self,
+        theme_wrap: TempThemes,
+        config_dir: &Path,
+    ) -> Result<Vec<(String, String)>> {
+        self.merge_index(theme_wrap, config_dir, false)
+    }
+
+    fn merge_index(
+        &mut self,
+        theme_wrap: TempThemes,
+        config_dir: &Path,
+        rename: bool,
+    ) -> Result<Vec<(String, String)>> {
+        self.definitions_version = CURRENT_DEFINITIONS_VERSION;
         let themes = theme_wrap.theme;
         trace!("Comparing themes");
+        let in_index: Vec<String> = themes.iter().map(|theme| theme.name.clone()).collect();
 
         // Get a list of existing themes.
         let existing_themes = Repo::installed_themes(config_dir)?;
         let current_theme = Repo::current_theme(config_dir)?.unwrap_or_default();
         let themes_dir = config_dir.join(THEMES_DIR);
+        let mut renamed = Vec::new();
 
         // Iterate over all the themes, and update/add if needed.
         for mut tema in themes {
             // Apply any theme changes before updating or adding it.
-            tema.apply_changes(config_dir)?;
+            let old_name = if rename {
+                Repo::rename_installed(config_dir, &mut tema, &self.themes)?
+            } else {
+                let old_name = tema.installed_old_name(config_dir, &self.themes);
+                if let Some(old_name) = &old_name {
+                    tema.directory = Some(themes_dir.join(old_name));
+                }
+                old_name
+            };
+            if let Some(old_name) = old_name {
+                // Keep the state of the entry under the old name.
+                if let Some(idx) = self.themes.iter().position(|t| t.name == old_name) {
+                    let old = self.themes.remove(idx);
+                    tema.current = tema.current.or(old.current);
+                }
+                renamed.push((old_name, tema.name.clone()));
+            }
 
             // Check if the theme is already installed and update the theme
             // directory attribute.
@@ -277,7 +437,69 @@ impl Repo {
 
             Repo::update_or_append(self, &tema);
         }
-        Ok(self)
+        if self.name != LOCAL_REPO_NAME {
+            self.mark_removed(&in_index);
+        }
+        Ok(renamed)
+    }
+
+    /// Moves the directory and overrides of a theme renamed upstream to its
+    /// new name, and re-points `current` if it was the current theme. Returns
+    /// the old name, if the theme was installed under one by an entry of
+    /// `owned`, the themes of its repository.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the theme cannot be renamed or `current` re-pointed.
+    pub fn rename_installed(
+        config_dir: &Path,
+        theme: &mut Theme,
+        owned: &[Theme],
+    ) -> Result<Option<String>> {
+        let themes_dir = config_dir.join(THEMES_DIR);
+        // Read before the rename, which leaves the symlink dangling.
+        let current_target = fs::read_link(themes_dir.join(CURRENT_DIR))
+            .ok()
+            .map(|target| themes_dir.join(target));
+        let Some(old_name) = theme.apply_changes(config_dir, owned)? else {
+            return Ok(None);
+        };
+        theme.directory = Some(themes_dir.join(&theme.name));
+        if current_target
+            .as_deref()
+            .is_some_and(|target| Repo::leads_to(config_dir, &old_name, target))
+        {
+            Repo::repoint_current(config_dir, theme)?;
+            theme.current = Some(true);
+        }
+        Ok(Some(old_name))
+    }
+
+    // Flags installed themes that are no longer in the repository's index, and
+    // drops the ones that aren't installed.
+    fn mark_removed(&mut self, in_index: &[String]) {
+        let repo_name = self.name.clone();
+        self.themes.retain_mut(|theme| {
+            if in_index.contains(&theme.name) {
+                return true;
+            }
+            if theme.directory.is_none() {
+                trace!("Dropping {} removed from {}", &theme.name, &repo_name);
+                return false;
+            }
+            if theme.removed_upstream != Some(true) {
+                println!(
+                    "{}",
+                    format!(
+                        "{} was removed from {repo_name}; it stays installed. Run `leftwm-theme adopt {}` to keep it as a local theme.",
+                        &theme.name, &theme.name
+                    )
+                    .bright_yellow()
+                );
+            }
+            theme.removed_upstream = Some(true);
+            true
+        });
     }
 
     pub fn update_or_append(repo: &mut Self, theme: &Theme) {
@@ -300,6 +522,11 @@ impl Repo {
                 target_theme.support_url.clone_from(&theme.support_url);
                 target_theme.set_relative_directory(theme.relative_directory.clone());
                 target_theme.dependencies.clone_from(&theme.dependencies);
+                target_theme.branch.clone_from(&theme.branch);
+                target_theme.renamed_from.clone_from(&theme.renamed_from);
+                target_theme.deprecated.clone_from(&theme.deprecated);
+                target_theme.replaced_by.clone_from(&theme.replaced_by);
+                target_theme.removed_upstream = None;
                 target_theme.directory.clone_from(&theme.directory);
             }
             // o/w insert a new leaf at the end
@@ -309,6 +536,34 @@ impl Repo {
         }
     }
 
+    /// Points the current symlink to a theme, e.g. after it was renamed or
+    /// upgraded, regenerating its copy with overrides if it has any.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the overrides cannot be applied or the symlink replaced.
+    pub fn repoint_current(config_path: &Path, theme: &Theme) -> Result<()> {
+        let themes_dir = config_path.join(THEMES_DIR);
+        let mut path = themes_dir.join(&theme.name);
+        if let Some(rel_dir) = theme.relative_directory() {
+            path.push(rel_dir);
+        }
+        let path = overrides::resolve(config_path, &theme.name, &path)?;
+        let current = themes_dir.join(CURRENT_DIR);
+        if current.symlink_metadata().is_ok() {
+            fs::remove_file(&current)?;
+        }
+        unix::fs::symlink(path, current)?;
+        Ok(())
+    }
+
+    // Whether `target`, read from the current symlink, is a theme's directory,
+    // one inside it (with `relative_directory`) or its copy with overrides.
+    fn leads_to(config_path: &Path, theme_name: &str, target: &Path) -> bool {
+        target.starts_with(config_path.join(THEMES_DIR).join(theme_name))
+            || target == overrides::generated_dir(config_path, theme_name)
+    }
+
     // Looks for the current theme in the themes directory and returns the name
     // of the current theme.
     fn current_theme(config_path: &Path) -> Result<Option<String>> {
@@ -522,14 +777,92 @@ mod test {
             .unwrap()
             .ends_with("/.config/leftwm/"));
 
-        let config2 = Config::new(Some(PathBuf::from("/tmp/foo")));
+        let tmpdir = tempfile::tempdir().unwrap();
+        let foo = tmpdir.path().join("foo");
+        let config2 = Config::new(Some(foo.clone()));
         assert!(config2.config_dir.is_some());
-        assert!(config2
-            .get_config_dir()
+        assert_eq!(config2.get_config_dir().unwrap(), foo);
+        assert!(foo.is_dir());
+    }
+
+    #[test]
+    fn test_config_load_keeps_config_dir() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let dir = tmpdir.path().join("leftwm");
+        // The first load writes a default themes.toml.
+        let config = Config::new(Some(dir.clone())).load().unwrap();
+        assert_eq!(config.config_dir.as_ref(), Some(&dir));
+        assert_eq!(config.repos.len(), 2);
+
+        Config::save(&config).unwrap();
+        let saved = fs::read_to_string(dir.join(THEMES_CONFIG_FILENAME)).unwrap();
+        assert!(!saved.contains("config_dir"));
+        let config = Config::new(Some(dir.clone())).load().unwrap();
+        assert_eq!(config.config_dir, Some(dir));
+    }
+
+    #[test]
+    fn test_config_save_atomically() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let dotfiles = tmpdir.path().join("dotfiles");
+        let dir = tmpdir.path().join("leftwm");
+        fs::create_dir_all(&dotfiles).unwrap();
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dotfiles.join(THEMES_CONFIG_FILENAME), "repos = []").unwrap();
+        unix_fs::symlink(
+            dotfiles.join(THEMES_CONFIG_FILENAME),
+            dir.join(THEMES_CONFIG_FILENAME),
+        )
+        .unwrap();
+
+        let config = Config::new(Some(dir.clone())).default();
+        Config::save(&config).unwrap();
+        // The symlink is kept, and its target replaced.
+        assert!(fs::symlink_metadata(dir.join(THEMES_CONFIG_FILENAME))
             .unwrap()
-            .to_str()
+            .file_type()
+            .is_symlink());
+        let saved = fs::read_to_string(dotfiles.join(THEMES_CONFIG_FILENAME)).unwrap();
+        assert!(saved.contains(COMMUNITY_REPO_NAME));
+        // No temporary files are left behind.
+        assert_eq!(fs::read_dir(&dotfiles).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_config_load_invalid() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config_filename = tmpdir.path().join(THEMES_CONFIG_FILENAME);
+        let invalid = "[[repos]]\nurl = \"localhost\"\nname = LOCAL\n";
+        fs::write(&config_filename, invalid).unwrap();
+
+        let config = Config::new(Some(tmpdir.path().to_path_buf()));
+        let Err(err) = config.load() else {
+            panic!("invalid themes.toml loaded");
+        };
+        let message = err.to_string();
+        assert!(message.contains("line 3"), "{}", message);
+        // The file is left alone, and backed up once.
+        assert_eq!(fs::read_to_string(&config_filename).unwrap(), invalid);
+        assert!(config.load().is_err());
+        let backups: Vec<PathBuf> = fs::read_dir(tmpdir.path())
             .unwrap()
-            .eq("/tmp/foo"));
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|extension| extension == "bak"))
+            .collect();
+        assert_eq!(backups.len(), 1);
+        assert!(message.contains(&backups[0].display().to_string()));
+        assert_eq!(fs::read_to_string(&backups[0]).unwrap(), invalid);
+    }
+
+    #[test]
+    fn test_config_lock() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::new(Some(tmpdir.path().to_path_buf()));
+        let lock = config.lock().unwrap();
+        let other = File::open(tmpdir.path().join(CONFIG_LOCK_FILENAME)).unwrap();
+        assert!(other.try_lock_exclusive().is_err());
+        drop(lock);
+        assert!(other.try_lock_exclusive().is_ok());
     }
 
     #[test]
@@ -540,6 +873,111 @@ mod test {
         assert!(config.repos.iter().any(|x| x.name == LOCAL_REPO_NAME));
     }
 
+    #[test]
+    fn test_repo_compare_renamed_theme() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let themes_dir = tmpdir.path().join(THEMES_DIR);
+        let old_dir = themes_dir.join("theme-x");
+        assert!(fs::create_dir_all(&old_dir).is_ok());
+        assert!(unix_fs::symlink(&old_dir, themes_dir.join(CURRENT_DIR)).is_ok());
+
+        let mut old = Theme::new("theme-x", None, Some(old_dir));
+        old.current(true);
+        let mut repo = Repo {
+            url: String::from("file:///known.toml"),
+            name: String::from("test-repo"),
+            definitions_version: CURRENT_DEFINITIONS_VERSION,
+            themes: vec![old],
+        };
+        let mut renamed = Theme::new("theme-y", None, None);
+        renamed.renamed_from = Some(vec![String::from("theme-x")]);
+        let index = TempThemes {
+            definitions_version: CURRENT_DEFINITIONS_VERSION,
+            theme: vec![renamed],
+        };
+
+        assert!(repo.compare(index, tmpdir.path()).is_ok());
+        assert_eq!(repo.themes.len(), 1);
+        let theme = &repo.themes[0];
+        assert_eq!(theme.name, "theme-y");
+        assert_eq!(theme.directory, Some(themes_dir.join("theme-y")));
+        assert_eq!(theme.current, Some(true));
+        assert_eq!(
+            fs::read_link(themes_dir.join(CURRENT_DIR)).unwrap(),
+            themes_dir.join("theme-y")
+        );
+        assert_eq!(
+            Repo::current_theme(tmpdir.path()).unwrap().as_deref(),
+            Some("theme-y")
+        );
+    }
+
+    #[test]
+    fn test_repo_compare_renamed_theme_with_relative_directory() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let themes_dir = tmpdir.path().join(THEMES_DIR);
+        let old_dir = themes_dir.join("theme-x");
+        assert!(fs::create_dir_all(old_dir.join("theme")).is_ok());
+        assert!(unix_fs::symlink(old_dir.join("theme"), themes_dir.join(CURRENT_DIR)).is_ok());
+
+        let mut old = Theme::new("theme-x", None, Some(old_dir));
+        old.set_relative_directory(Some(String::from("theme")));
+        let mut repo = Repo {
+            url: String::from("file:///known.toml"),
+            name: String::from("test-repo"),
+            definitions_version: CURRENT_DEFINITIONS_VERSION,
+            themes: vec![old],
+        };
+        let mut renamed = Theme::new("theme-y", None, None);
+        renamed.renamed_from = Some(vec![String::from("theme-x")]);
+        renamed.set_relative_directory(Some(String::from("theme")));
+        let index = TempThemes {
+            definitions_version: CURRENT_DEFINITIONS_VERSION,
+            theme: vec![renamed],
+        };
+
+        assert!(repo.compare(index, tmpdir.path()).is_ok());
+        assert_eq!(repo.themes[0].current, Some(true));
+        assert_eq!(
+            fs::read_link(themes_dir.join(CURRENT_DIR)).unwrap(),
+            themes_dir.join("theme-y").join("theme")
+        );
+    }
+
+    #[test]
+    fn test_repo_compare_removed_themes() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let installed = Theme::new("installed", None, Some(tmpdir.path().join("installed")));
+        let uninstalled = Theme::new("uninstalled", None, None);
+        let kept = Theme::new("kept", None, None);
+        let mut repo = Repo {
+            url: String::from("file:///known.toml"),
+            name: String::from("test-repo"),
+            definitions_version: CURRENT_DEFINITIONS_VERSION,
+            themes: vec![installed, uninstalled, kept.clone()],
+        };
+        let mut deprecated = kept;
+        deprecated.deprecated = Some(String::from("unmaintained"));
+        deprecated.replaced_by = Some(String::from("other"));
+        let index = TempThemes {
+            definitions_version: CURRENT_DEFINITIONS_VERSION,
+            theme: vec![deprecated],
+        };
+
+        assert!(repo.compare(index, tmpdir.path()).is_ok());
+        let names: Vec<&str> = repo.themes.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["installed", "kept"]);
+        assert_eq!(repo.themes[0].removed_upstream, Some(true));
+        assert_eq!(
+            repo.themes[0].notice().as_deref(),
+            Some("removed from its repository")
+        );
+        assert_eq!(
+            repo.themes[1].notice().as_deref(),
+            Some("deprecated: unmaintained; replaced by other")
+        );
+    }
+
     #[test]
     fn test_config_update_local_repo() {
         // Create test config directory layout with community and local themes.
@@ -578,6 +1016,13 @@ mod test {
                 break;
             }
         }
+        // A theme imported as local before its repository was fetched, e.g.
+        // by `repair` offline, moves to that repository.
+        config.repos[1].themes.push(Theme::new(
+            "community-theme1",
+            None,
+            Some(themes_dir.join("community-theme1")),
+        ));
 
         assert!(config.update_local_repo().is_ok());
 
@@ -0,0 +1,183 @@
+//! Versions of the theme definitions format used by indexes (`known.toml`)
+//! and `themes.toml`.
+//!
+//! Older definitions are upgraded in place, one version at a time, before
+//! they are deserialized. Definitions from newer versions are read as far as
+//! they are understood: fields this version doesn't know are ignored.
+//!
+//! - v1: the original format.
+//! - v2: adds `branch`, `renamed_from`, `deprecated` and `replaced_by` to
+//!   themes, and replaces the `package` of a dependency by `packages`, keyed
+//!   by distribution.
+use crate::errors::Result;
+use crate::models::config::CURRENT_DEFINITIONS_VERSION;
+use crate::models::theme::{TempThemes, ANY_DISTRIBUTION};
+use log::warn;
+use std::convert::TryFrom;
+use toml::{Table, Value};
+
+const VERSION_KEY: &str = "definitions_version";
+
+/// Parses a theme index, upgrading it to the current definitions version.
+///
+/// # Errors
+///
+/// Will error if the index is not valid TOML or lacks fields every version has.
+pub fn parse_index(contents: &str) -> Result<TempThemes> {
+    let mut table: Table = toml::from_str(contents)?;
+    let version = version(&table);
+    if version > CURRENT_DEFINITIONS_VERSION {
+        warn!(
+            "The theme index uses definitions version {version}, newer than the {CURRENT_DEFINITIONS_VERSION} this version of leftwm-theme understands; unknown fields are ignored. Consider updating leftwm-theme."
+        );
+    }
+    if let Some(Value::Array(themes)) = table.get_mut("theme") {
+        upgrade(themes, version);
+    }
+    Ok(table.try_into()?)
+}
+
+/// Upgrades the themes stored for each repository of a `themes.toml`, and
+/// marks the repositories as being at the current version.
+pub fn upgrade_config(table: &mut Table) {
+    let Some(Value::Array(repos)) = table.get_mut("repos") else {
+        return;
+    };
+    for repo in repos.iter_mut().filter_map(Value::as_table_mut) {
+        let version = version(repo);
+        if version >= CURRENT_DEFINITIONS_VERSION {
+            continue;
+        }
+        if let Some(Value::Array(themes)) = repo.get_mut("themes") {
+            upgrade(themes, version);
+        }
+        repo.insert(
+            VERSION_KEY.to_string(),
+            Value::Integer(CURRENT_DEFINITIONS_VERSION.into()),
+        );
+    }
+}
+
+// The version of a table of definitions. Definitions without one, or with 0
+// (the default written by versions that didn't know about it), are v1.
+fn version(table: &Table) -> i16 {
+    table
+        .get(VERSION_KEY)
+        .and_then(Value::as_integer)
+        .and_then(|version| i16::try_from(version).ok())
+        .unwrap_or(1)
+        .max(1)
+}
+
+/// Upgrades themes from definitions version `from` to the current version.
+pub fn upgrade(themes: &mut [Value], from: i16) {
+    for theme in themes.iter_mut().filter_map(Value::as_table_mut) {
+        if from < 2 {
+            v1_to_v2(theme);
+        }
+    }
+}
+
+// v2 replaced the single `package` of a dependency by `packages`, keyed by
+// distribution.
+fn v1_to_v2(theme: &mut Table) {
+    let Some(Value::Array(dependencies)) = theme.get_mut("dependencies") else {
+        return;
+    };
+    for dependency in dependencies.iter_mut().filter_map(Value::as_table_mut) {
+        let Some(package) = dependency.remove("package") else {
+            continue;
+        };
+        if dependency.contains_key("packages") {
+            continue;
+        }
+        let mut packages = Table::new();
+        packages.insert(ANY_DISTRIBUTION.to_string(), package);
+        dependency.insert("packages".to_string(), Value::Table(packages));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::models::Config;
+
+    #[test]
+    fn test_parse_index_v1() {
+        let index = parse_index(
+            r#"
+[[theme]]
+name = "forest"
+repository = "https://github.com/me/forest"
+dependencies = [{ program = "polybar", package = "polybar-git" }]
+"#,
+        )
+        .unwrap();
+        assert_eq!(index.definitions_version, 0);
+        let dependency = &index.theme[0].dependencies.as_ref().unwrap()[0];
+        assert_eq!(dependency.package_for(Some("arch")), Some("polybar-git"));
+    }
+
+    #[test]
+    fn test_parse_index_v2() {
+        let index = parse_index(
+            r#"
+definitions_version = 2
+
+[[theme]]
+name = "forest"
+branch = "main"
+renamed_from = ["green"]
+dependencies = [{ program = "polybar", packages = { arch = "polybar-git", "*" = "polybar" } }]
+"#,
+        )
+        .unwrap();
+        let theme = &index.theme[0];
+        assert_eq!(theme.branch.as_deref(), Some("main"));
+        assert_eq!(theme.renamed_from, Some(vec![String::from("green")]));
+        let dependency = &theme.dependencies.as_ref().unwrap()[0];
+        assert_eq!(dependency.package_for(Some("arch")), Some("polybar-git"));
+        assert_eq!(dependency.package_for(Some("debian")), Some("polybar"));
+        assert_eq!(dependency.package_for(None), Some("polybar"));
+    }
+
+    #[test]
+    fn test_parse_index_newer() {
+        let index = parse_index(
+            r#"
+definitions_version = 99
+shiny_new_thing = true
+
+[[theme]]
+name = "forest"
+mirrors = ["https://example.com/forest"]
+"#,
+        )
+        .unwrap();
+        assert_eq!(index.definitions_version, 99);
+        assert_eq!(index.theme[0].name, "forest");
+    }
+
+    #[test]
+    fn test_upgrade_config() {
+        let mut table: Table = toml::from_str(
+            r#"
+[[repos]]
+url = "localhost"
+name = "LOCAL"
+definitions_version = 1
+
+[[repos.themes]]
+name = "forest"
+dependencies = [{ program = "polybar", package = "polybar-git" }]
+"#,
+        )
+        .unwrap();
+        upgrade_config(&mut table);
+        let config: Config = table.try_into().unwrap();
+        let repo = &config.repos[0];
+        assert_eq!(repo.definitions_version, CURRENT_DEFINITIONS_VERSION);
+        let dependency = &repo.themes[0].dependencies.as_ref().unwrap()[0];
+        assert_eq!(dependency.package_for(None), Some("polybar-git"));
+    }
+}
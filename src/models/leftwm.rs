@@ -1,21 +1,300 @@
-use crate::errors::{LeftError, Result};
+use crate::errors::Result;
+use log::{trace, warn};
+use regex::Regex;
+use semver::Version;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::process::Command;
-use std::str;
+use std::sync::OnceLock;
+use std::time::UNIX_EPOCH;
+use xdg::BaseDirectories;
 
-#[derive(Debug)]
+const CACHE_DIR_PREFIX: &str = "leftwm-theme";
+const VERSION_CACHE_FILENAME: &str = "leftwm-version.toml";
+
+// Programs (and their arguments) asked for the installed version, in order.
+const PROBES: &[(&str, &str)] = &[("leftwm-state", "-V"), ("leftwm", "--version")];
+
+// Version of leftwm-core this binary was built against, see build.rs.
+const COMPILED_CORE_VERSION: Option<&str> = option_env!("LEFTWM_CORE_VERSION");
+
+// The version is resolved at most once per process.
+static LEFTWM: OnceLock<LeftWm> = OnceLock::new();
+
+#[derive(Debug, Clone)]
 pub struct LeftWm {
-    pub version: String,
+    /// The installed version, or `None` if it could not be determined
+    pub version: Option<Version>,
+    /// Whether `version` is the one leftwm-theme was built against, because
+    /// `LeftWM` could not be asked for its own
+    pub assumed: bool,
+}
+
+/// On-disk record of the last probe result, keyed on the probed binary's mtime.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+struct VersionCache {
+    binary: PathBuf,
+    mtime: u64,
+    version: String,
 }
 
 impl LeftWm {
-    /// # Errors
+    /// Returns the installed `LeftWM` version. The version is probed once per
+    /// process and cached on disk until the probed binary changes.
     ///
-    /// Will error if output of leftwm-state fails to be obtained.
-    /// Will error if leftwm-state output can't be parsed
-    pub fn get() -> Result<Self> {
-        let version_raw = &Command::new("leftwm-state").arg("-V").output()?.stdout;
-        let version_utf8 = str::from_utf8(version_raw).map_err(|_| LeftError::from("UTF Error"))?;
-        let version = version_utf8.replace("LeftWM State ", "").replace('\n', "");
-        Ok(LeftWm { version })
+    /// Tries `leftwm-state -V`, then `leftwm --version`, then falls back to the
+    /// version of `leftwm-core` this tool was compiled against.
+    pub fn get() -> Self {
+        LEFTWM.get_or_init(LeftWm::probe).clone()
+    }
+
+    /// Overrides the detected `LeftWM` version for the rest of the process, e.g.
+    /// from `--leftwm-version` or `LEFTWM_VERSION`. Has no effect once the
+    /// version has already been resolved.
+    pub fn set_version(version: &str) {
+        trace!("Overriding LeftWM version with {version}");
+        let parsed = parse_version(version);
+        if parsed.is_none() {
+            warn!("Could not parse LeftWM version {version:?}, treating it as unknown");
+        }
+        let _ = LEFTWM.set(LeftWm {
+            version: parsed,
+            assumed: false,
+        });
+    }
+
+    /// Returns the version as a printable string.
+    pub fn version_string(&self) -> String {
+        match &self.version {
+            Some(version) if self.assumed => format!("{version} (assumed)"),
+            Some(version) => version.to_string(),
+            None => String::from("unknown"),
+        }
+    }
+
+    /// Explains that the version was assumed, if it was, for output that
+    /// depends on it.
+    pub fn assumed_note(&self) -> Option<String> {
+        let version = self.version.as_ref().filter(|_| self.assumed)?;
+        Some(format!(
+            "LeftWM did not report its version; assuming {version}, the version leftwm-theme was built against. Use --leftwm-version to set it."
+        ))
+    }
+
+    fn probe() -> Self {
+        let cache_file = BaseDirectories::with_prefix(CACHE_DIR_PREFIX)
+            .place_cache_file(VERSION_CACHE_FILENAME)
+            .ok();
+
+        for (program, arg) in PROBES {
+            let Some(binary) = which(program) else {
+                trace!("{program} not found in $PATH");
+                continue;
+            };
+            let mtime = mtime(&binary);
+
+            if let (Some(mtime), Some(cache_file)) = (mtime, &cache_file) {
+                if let Some(version) = read_cache(cache_file, &binary, mtime) {
+                    trace!("Using cached LeftWM version {version}");
+                    return LeftWm {
+                        version: Some(version),
+                        assumed: false,
+                    };
+                }
+            }
+
+            let Some(version) = run_probe(&binary, arg) else {
+                continue;
+            };
+            if let (Some(mtime), Some(cache_file)) = (mtime, &cache_file) {
+                let cache = VersionCache {
+                    binary,
+                    mtime,
+                    version: version.to_string(),
+                };
+                if let Err(err) = write_cache(cache_file, &cache) {
+                    trace!("Could not cache LeftWM version: {err}");
+                }
+            }
+            return LeftWm {
+                version: Some(version),
+                assumed: false,
+            };
+        }
+
+        let version = COMPILED_CORE_VERSION.and_then(parse_version);
+        if let Some(version) = &version {
+            warn!("Could not ask LeftWM for its version, assuming {version}");
+        } else {
+            warn!("Could not determine the LeftWM version");
+        }
+        LeftWm {
+            assumed: version.is_some(),
+            version,
+        }
+    }
+}
+
+// Runs a version probe and parses its output.
+fn run_probe(binary: &Path, arg: &str) -> Option<Version> {
+    let output = match Command::new(binary).arg(arg).output() {
+        Ok(output) => output,
+        Err(err) => {
+            trace!("Could not run {}: {err}", binary.display());
+            return None;
+        }
+    };
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let version = parse_version(&stdout);
+    if version.is_none() {
+        trace!("Unrecognised output from {}: {stdout:?}", binary.display());
+    }
+    version
+}
+
+/// Extracts a version from the output of a version command, such as
+/// `LeftWM State 0.5.1`, `leftwm 0.5.0-rc.1` or `LeftWM 0.5.1-12-gdeadbee`.
+///
+/// A `git describe` suffix (commits since the tag and the abbreviated hash) is
+/// kept as build metadata, so the version compares like the tagged release.
+pub fn parse_version(output: &str) -> Option<Version> {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let re = RE.get_or_init(|| {
+        Regex::new(
+            r"(?x)
+            (?P<major>\d+)\.(?P<minor>\d+)(?:\.(?P<patch>\d+))?
+            (?P<describe>-\d+-g[0-9a-f]+(?:-dirty)?)?
+            (?:-(?P<pre>[0-9A-Za-z.-]+?))?
+            (?:-dirty)?
+            (?:\+(?P<build>[0-9A-Za-z.-]+))?
+            (?:\s|$)",
+        )
+        .expect("valid regex")
+    });
+    let caps = re.captures(output.trim())?;
+    let mut version = Version::new(
+        caps["major"].parse().ok()?,
+        caps["minor"].parse().ok()?,
+        caps.name("patch")
+            .map_or(Some(0), |p| p.as_str().parse().ok())?,
+    );
+    if let Some(pre) = caps.name("pre") {
+        version.pre = semver::Prerelease::new(pre.as_str()).ok()?;
+    }
+    let build = caps
+        .name("describe")
+        .map(|d| d.as_str().trim_start_matches('-').replace('-', "."))
+        .or_else(|| caps.name("build").map(|b| b.as_str().to_string()));
+    if let Some(build) = build {
+        version.build = semver::BuildMetadata::new(&build).ok()?;
+    }
+    Some(version)
+}
+
+// Looks up a program in $PATH and returns its full path.
+fn which(program: &str) -> Option<PathBuf> {
+    env::var_os("PATH").and_then(|path| {
+        env::split_paths(&path)
+            .map(|dir| dir.join(program))
+            .find(|candidate| candidate.is_file())
+    })
+}
+
+fn mtime(path: &Path) -> Option<u64> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_secs())
+}
+
+// Returns the cached version if it was recorded for the same binary and mtime.
+fn read_cache(cache_file: &Path, binary: &Path, mtime: u64) -> Option<Version> {
+    let contents = fs::read_to_string(cache_file).ok()?;
+    let cache: VersionCache = toml::from_str(&contents).ok()?;
+    if cache.binary == binary && cache.mtime == mtime {
+        Version::parse(&cache.version).ok()
+    } else {
+        None
+    }
+}
+
+fn write_cache(cache_file: &Path, cache: &VersionCache) -> Result<()> {
+    fs::write(cache_file, toml::to_string(cache)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_version_cache_roundtrip() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let cache_file = tmpdir.path().join(VERSION_CACHE_FILENAME);
+        let binary = PathBuf::from("/usr/bin/leftwm-state");
+        let cache = VersionCache {
+            binary: binary.clone(),
+            mtime: 42,
+            version: String::from("0.5.1"),
+        };
+        assert!(write_cache(&cache_file, &cache).is_ok());
+
+        assert_eq!(
+            read_cache(&cache_file, &binary, 42),
+            Some(Version::new(0, 5, 1))
+        );
+        // A rebuilt binary or a different binary invalidates the cache.
+        assert!(read_cache(&cache_file, &binary, 43).is_none());
+        assert!(read_cache(&cache_file, Path::new("/usr/local/bin/leftwm-state"), 42).is_none());
+    }
+
+    #[test]
+    fn test_version_cache_missing_or_invalid() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let cache_file = tmpdir.path().join(VERSION_CACHE_FILENAME);
+        let binary = PathBuf::from("/usr/bin/leftwm-state");
+        assert!(read_cache(&cache_file, &binary, 42).is_none());
+
+        fs::write(&cache_file, "not = [valid").unwrap();
+        assert!(read_cache(&cache_file, &binary, 42).is_none());
+    }
+
+    #[test]
+    fn test_assumed_version_is_reported() {
+        let assumed = LeftWm {
+            version: Some(Version::new(0, 5, 4)),
+            assumed: true,
+        };
+        assert_eq!(assumed.version_string(), "0.5.4 (assumed)");
+        assert!(assumed.assumed_note().unwrap().contains("assuming 0.5.4"));
+        let probed = LeftWm {
+            assumed: false,
+            ..assumed
+        };
+        assert_eq!(probed.version_string(), "0.5.4");
+        assert!(probed.assumed_note().is_none());
+    }
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(
+            parse_version("LeftWM State 0.5.1\n"),
+            Some(Version::new(0, 5, 1))
+        );
+        assert_eq!(parse_version("leftwm 0.4.2"), Some(Version::new(0, 4, 2)));
+        assert_eq!(parse_version("0.5"), Some(Version::new(0, 5, 0)));
+        assert_eq!(
+            parse_version("LeftWM 0.5.0-rc.1"),
+            Some(Version::parse("0.5.0-rc.1").unwrap())
+        );
+        assert_eq!(
+            parse_version("LeftWM State 0.5.1-12-gdeadbee"),
+            Some(Version::parse("0.5.1+12.gdeadbee").unwrap())
+        );
+        assert_eq!(
+            parse_version("LeftWM State 0.5.1-12-gdeadbee-dirty"),
+            Some(Version::parse("0.5.1+12.gdeadbee.dirty").unwrap())
+        );
+        assert!(parse_version("").is_none());
+        assert!(parse_version("command not found").is_none());
     }
 }
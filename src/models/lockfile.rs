@@ -0,0 +1,165 @@
+use crate::errors::{friendly_message, Result};
+use crate::models::Config;
+use crate::utils::git;
+use git2::Repository;
+use std::fs;
+use std::path::Path;
+
+/// The default name of a lock file.
+pub const LOCK_FILE: &str = "themes.lock";
+
+/// An exact set of installed themes, written by `leftwm-theme lock` and
+/// reproduced by `leftwm-theme sync`.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct LockFile {
+    /// Name of the current theme
+    pub current: Option<String>,
+    #[serde(default)]
+    pub theme: Vec<LockedTheme>,
+}
+
+/// An installed theme, pinned to the commit it had when locked.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LockedTheme {
+    pub name: String,
+    /// Name of the repository (known.toml) listing the theme
+    pub repo: String,
+    /// URL of the repository's known.toml
+    pub repo_url: String,
+    /// The git repository the theme is cloned from
+    pub repository: String,
+    /// The commit checked out
+    pub commit: String,
+    /// Path to the directory containing up, down, and theme.toml w.r.t. root
+    pub relative_directory: Option<String>,
+}
+
+impl LockFile {
+    /// Locks the installed themes of a config. Themes which cannot be locked,
+    /// e.g. local themes without a remote, are returned with the reason.
+    pub fn from_config(config: &Config) -> (LockFile, Vec<(String, String)>) {
+        let mut lock = LockFile::default();
+        let mut skipped = Vec::new();
+        for repo in &config.repos {
+            for theme in &repo.themes {
+                let Some(directory) = &theme.directory else {
+                    continue;
+                };
+                if theme.current == Some(true) {
+                    lock.current = Some(theme.name.clone());
+                }
+                let commit = match git::head_commit(directory) {
+                    Ok(commit) => commit,
+                    Err(err) => {
+                        skipped.push((theme.name.clone(), err.message().to_string()));
+                        continue;
+                    }
+                };
+                let Some(repository) = theme
+                    .repository
+                    .clone()
+                    .filter(|repository| !Path::new(repository).is_absolute())
+                    .or_else(|| origin_url(directory))
+                else {
+                    skipped.push((
+                        theme.name.clone(),
+                        String::from("it has no remote repository to clone it from"),
+                    ));
+                    continue;
+                };
+                lock.theme.push(LockedTheme {
+                    name: theme.name.clone(),
+                    repo: repo.name.clone(),
+                    repo_url: repo.url.clone(),
+                    repository,
+                    commit,
+                    relative_directory: theme.relative_directory(),
+                });
+            }
+        }
+        (lock, skipped)
+    }
+
+    /// # Errors
+    ///
+    /// Will error if the file cannot be read or parsed.
+    pub fn load(path: &Path) -> Result<LockFile> {
+        let contents = fs::read_to_string(path).map_err(|err| {
+            friendly_message(&format!("Could not read {}: {err}", path.display()))
+        })?;
+        Ok(toml::from_str(&contents)?)
+    }
+
+    /// # Errors
+    ///
+    /// Will error if the file cannot be written.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let contents = format!(
+            "# Generated by `leftwm-theme lock`; reproduce with `leftwm-theme sync`.\n{}",
+            toml::to_string(self)?
+        );
+        fs::write(path, contents)?;
+        Ok(())
+    }
+}
+
+// The URL of the `origin` remote of a theme, unless it is a local path.
+fn origin_url(directory: &Path) -> Option<String> {
+    let repo = Repository::open(directory).ok()?;
+    let remote = repo.find_remote("origin").ok()?;
+    remote
+        .url()
+        .filter(|url| !Path::new(url).is_absolute())
+        .map(String::from)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::models::Theme;
+
+    #[test]
+    fn test_from_config() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let forest = tmpdir.path().join("forest");
+        Repository::init(&forest).unwrap();
+        fs::write(forest.join("up"), "#!/bin/sh").unwrap();
+        let commit = git::commit_files(&forest, &[&forest.join("up")], "Initial").unwrap();
+        let mine = tmpdir.path().join("mine");
+        Repository::init(&mine).unwrap();
+        fs::write(mine.join("up"), "#!/bin/sh").unwrap();
+        git::commit_files(&mine, &[&mine.join("up")], "Initial").unwrap();
+
+        let mut config = Config::new(None).default();
+        let mut theme = Theme::new("forest", None, Some(forest));
+        theme.repository = Some(String::from("https://github.com/me/forest"));
+        theme.current(true);
+        config.repos[0].themes.push(theme);
+        config.repos[0]
+            .themes
+            .push(Theme::new("not-installed", None, None));
+        let mut local = Theme::new("mine", None, Some(mine.clone()));
+        local.repository = Some(mine.display().to_string());
+        config.repos[1].themes.push(local);
+
+        let (lock, skipped) = LockFile::from_config(&config);
+        assert_eq!(lock.current.as_deref(), Some("forest"));
+        assert_eq!(
+            lock.theme,
+            vec![LockedTheme {
+                name: String::from("forest"),
+                repo: config.repos[0].name.clone(),
+                repo_url: config.repos[0].url.clone(),
+                repository: String::from("https://github.com/me/forest"),
+                commit: commit.to_string(),
+                relative_directory: None,
+            }]
+        );
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].0, "mine");
+
+        let path = tmpdir.path().join(LOCK_FILE);
+        lock.save(&path).unwrap();
+        assert_eq!(LockFile::load(&path).unwrap(), lock);
+    }
+}
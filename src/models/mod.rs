@@ -1,7 +1,16 @@
 pub mod config;
+pub mod definitions;
 mod leftwm;
+pub mod lockfile;
+pub mod template;
 mod theme;
+pub mod theme_file;
+pub mod wanted;
 
 pub use config::{Config, Repo, THEMES_DIR};
 pub use leftwm::LeftWm;
-pub use theme::{DependencyL, Theme};
+pub use lockfile::{LockFile, LockedTheme};
+pub use template::{Template, TemplateSource};
+pub use theme::{DependencyL, TempThemes, Theme, INDEX_ENTRY_FILE};
+pub use theme_file::ThemeFile;
+pub use wanted::{Wanted, WantedRepo, WantedTheme};
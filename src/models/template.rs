@@ -0,0 +1,493 @@
+//! Templates a new theme can be prefilled from.
+//!
+//! A template may declare variables in a `template.toml`; every `{{ variable }}`
+//! in its files is replaced when a theme is created from it.
+use crate::errors::{friendly_message, Result};
+use crate::models::Config;
+use git2::Repository;
+use log::{trace, warn};
+use regex::{Captures, Regex};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+use xdg::BaseDirectories;
+
+/// Directory in the leftwm config directory holding the user's own templates.
+pub const TEMPLATES_DIR: &str = "theme-templates";
+
+/// File of a template declaring its variables; it is not copied into themes.
+pub const TEMPLATE_CONFIG_FILE: &str = "template.toml";
+
+/// Name of the template built into leftwm-theme.
+pub const BUILTIN_TEMPLATE: &str = "default";
+
+// Files of the built-in template, and whether they are executable.
+const BUILTIN_FILES: &[(&str, &str, bool)] = &[
+    (
+        TEMPLATE_CONFIG_FILE,
+        include_str!("../../templates/default/template.toml"),
+        false,
+    ),
+    ("up", include_str!("../../templates/default/up"), true),
+    ("down", include_str!("../../templates/default/down"), true),
+    (
+        "theme.ron",
+        include_str!("../../templates/default/theme.ron"),
+        false,
+    ),
+    (
+        "README.md",
+        include_str!("../../templates/default/README.md"),
+        false,
+    ),
+    (
+        "index-entry.toml",
+        include_str!("../../templates/default/index-entry.toml"),
+        false,
+    ),
+];
+
+/// Where a template comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemplateSource {
+    /// `~/.config/leftwm/theme-templates/`
+    User,
+    /// `leftwm/themes` in the XDG data directories, e.g. `/usr/share/leftwm/themes/`
+    System,
+    /// An installed theme
+    Installed,
+    /// A directory given by path
+    Path,
+    /// A git repository, cloned when used
+    Git,
+    /// Built into leftwm-theme
+    Builtin,
+}
+
+impl fmt::Display for TemplateSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TemplateSource::User => "user",
+            TemplateSource::System => "system",
+            TemplateSource::Installed => "installed theme",
+            TemplateSource::Path => "directory",
+            TemplateSource::Git => "git",
+            TemplateSource::Builtin => "built in",
+        })
+    }
+}
+
+/// A template: a directory, or a git repository, whose files seed a new theme.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    pub name: String,
+    pub source: TemplateSource,
+    /// The template's directory, or the URL of its repository
+    pub location: String,
+}
+
+/// The files of a template, ready to be copied. A cloned repository is
+/// removed when this is dropped.
+#[derive(Debug)]
+pub struct TemplateDir {
+    pub path: PathBuf,
+    _clone: Option<TempDir>,
+}
+
+/// Contents of a `template.toml`.
+#[derive(Deserialize, Debug, Default)]
+pub struct TemplateConfig {
+    #[serde(default)]
+    pub variables: Vec<Variable>,
+}
+
+/// A variable declared by a template.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Variable {
+    pub name: String,
+    /// Question asked for the value; the name is used if missing
+    pub prompt: Option<String>,
+    #[serde(default)]
+    pub default: String,
+}
+
+impl TemplateDir {
+    /// Returns the variables declared in the template's `template.toml`.
+    ///
+    /// # Errors
+    ///
+    /// Will error if `template.toml` exists but is not valid.
+    pub fn variables(&self) -> Result<Vec<Variable>> {
+        let path = self.path.join(TEMPLATE_CONFIG_FILE);
+        if !path.is_file() {
+            return Ok(Vec::new());
+        }
+        let config: TemplateConfig = toml::from_str(&fs::read_to_string(&path)?)
+            .map_err(|err| friendly_message(&format!("Invalid {}: {err}", path.display())))?;
+        Ok(config.variables)
+    }
+}
+
+impl Template {
+    /// Lists the templates found in the user and system template directories,
+    /// and the installed themes, in that order.
+    ///
+    /// # Errors
+    ///
+    /// Will error if the config directory cannot be determined.
+    pub fn list(config: &mut Config) -> Result<Vec<Template>> {
+        let mut templates = directories(
+            &config.get_config_dir()?.join(TEMPLATES_DIR),
+            TemplateSource::User,
+        );
+        for dir in BaseDirectories::with_prefix("leftwm").list_data_files_once("themes") {
+            if dir.is_dir() {
+                templates.push(Template::from_dir(&dir, TemplateSource::System));
+            }
+        }
+        for theme in config.themes(false) {
+            let Some(mut dir) = theme.directory.clone() else {
+                continue;
+            };
+            if let Some(rel_dir) = theme.relative_directory() {
+                dir.push(rel_dir);
+            }
+            templates.push(Template {
+                name: theme.name.clone(),
+                source: TemplateSource::Installed,
+                location: dir.display().to_string(),
+            });
+        }
+        templates.push(Template {
+            name: String::from(BUILTIN_TEMPLATE),
+            source: TemplateSource::Builtin,
+            location: String::from("leftwm-theme"),
+        });
+        Ok(templates)
+    }
+
+    /// Finds a template by name, or takes `wanted` as a git URL or a directory.
+    ///
+    /// # Errors
+    ///
+    /// Will error if no template matches.
+    pub fn find(config: &mut Config, wanted: &str) -> Result<Template> {
+        if is_git_url(wanted) {
+            return Ok(Template {
+                name: wanted.to_string(),
+                source: TemplateSource::Git,
+                location: wanted.to_string(),
+            });
+        }
+        if let Some(template) = Template::list(config)?
+            .into_iter()
+            .find(|template| template.name == wanted)
+        {
+            return Ok(template);
+        }
+        let path = Path::new(wanted);
+        if path.is_dir() {
+            return Ok(Template::from_dir(path, TemplateSource::Path));
+        }
+        Err(friendly_message(&format!(
+            "No template named {wanted}. See `leftwm-theme new --list-templates`."
+        )))
+    }
+
+    /// Returns the template's files, cloning its repository or writing out the
+    /// built-in template first if needed.
+    ///
+    /// # Errors
+    ///
+    /// Will error if the repository cannot be cloned or the files written.
+    pub fn fetch(&self) -> Result<TemplateDir> {
+        if self.source == TemplateSource::Builtin {
+            let files = tempfile::tempdir()?;
+            for (name, contents, executable) in BUILTIN_FILES {
+                let path = files.path().join(name);
+                fs::write(&path, contents)?;
+                if *executable {
+                    fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+                }
+            }
+            return Ok(TemplateDir {
+                path: files.path().to_path_buf(),
+                _clone: Some(files),
+            });
+        }
+        if self.source == TemplateSource::Git {
+            trace!("Cloning template {}", &self.location);
+            let clone = tempfile::tempdir()?;
+            Repository::clone(&self.location, clone.path())?;
+            return Ok(TemplateDir {
+                path: clone.path().to_path_buf(),
+                _clone: Some(clone),
+            });
+        }
+        Ok(TemplateDir {
+            path: PathBuf::from(&self.location),
+            _clone: None,
+        })
+    }
+
+    fn from_dir(dir: &Path, source: TemplateSource) -> Template {
+        Template {
+            name: dir
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            source,
+            location: dir.display().to_string(),
+        }
+    }
+}
+
+// The subdirectories of `dir`, as templates.
+fn directories(dir: &Path, source: TemplateSource) -> Vec<Template> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut templates: Vec<Template> = entries
+        .filter_map(std::result::Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .map(|path| Template::from_dir(&path, source))
+        .collect();
+    templates.sort_by(|a, b| a.name.cmp(&b.name));
+    templates
+}
+
+fn placeholder_regex() -> Regex {
+    Regex::new(r"\{\{\s*([A-Za-z_][A-Za-z0-9_]*)\s*\}\}").expect("valid regex")
+}
+
+// Values are pasted as is into RON, TOML and shell scripts; these characters
+// would break out of their strings there.
+const UNSAFE_CHARS: &[char] = &['"', '\'', '\\', '`', '$', '\n', '\r'];
+
+/// Checks that a variable's value can be substituted into any template file
+/// without escaping.
+///
+/// # Errors
+///
+/// Describes the problem if the value contains quotes, backslashes, `$`,
+/// backticks or line breaks.
+pub fn check_value(value: &str) -> std::result::Result<(), String> {
+    match value.chars().find(|c| UNSAFE_CHARS.contains(c)) {
+        Some(c) => Err(format!(
+            "{c:?} is not allowed in template values; quotes, backslashes, `$`, backticks and line breaks would break the generated files"
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Replaces `{{ variable }}` placeholders with their values, leaving unknown
+/// ones as they are. Returns the result and the names of unknown variables.
+pub fn substitute(text: &str, values: &BTreeMap<String, String>) -> (String, BTreeSet<String>) {
+    let mut unknown = BTreeSet::new();
+    let result = placeholder_regex().replace_all(text, |caps: &Captures| {
+        if let Some(value) = values.get(&caps[1]) {
+            value.clone()
+        } else {
+            unknown.insert(caps[1].to_string());
+            caps[0].to_string()
+        }
+    });
+    (result.into_owned(), unknown)
+}
+
+/// Substitutes variables into every text file below `dir`, leaving out the
+/// git repository, symlinks and binary files.
+///
+/// # Errors
+///
+/// Will error if a file cannot be read or written.
+pub fn render_dir(dir: &Path, values: &BTreeMap<String, String>) -> Result<()> {
+    let mut unknown = BTreeSet::new();
+    render_files(dir, values, &mut unknown)?;
+    for name in unknown {
+        warn!("No value for template variable `{name}`; it was left as is");
+    }
+    Ok(())
+}
+
+fn render_files(
+    dir: &Path,
+    values: &BTreeMap<String, String>,
+    unknown: &mut BTreeSet<String>,
+) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let path = entry.path();
+        if entry.file_name() == ".git" || file_type.is_symlink() {
+            continue;
+        }
+        if file_type.is_dir() {
+            render_files(&path, values, unknown)?;
+            continue;
+        }
+        let Ok(contents) = fs::read_to_string(&path) else {
+            trace!("Not substituting into binary file {}", path.display());
+            continue;
+        };
+        let (rendered, missing) = substitute(&contents, values);
+        unknown.extend(missing);
+        if rendered != contents {
+            // Writing to the existing file keeps its permissions.
+            fs::write(&path, rendered)?;
+        }
+    }
+    Ok(())
+}
+
+/// Whether a template argument is a git URL rather than a name.
+pub fn is_git_url(wanted: &str) -> bool {
+    wanted.contains("://")
+        || wanted.starts_with("git@")
+        || Path::new(wanted)
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("git"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::models::Theme;
+    use std::fs;
+
+    #[test]
+    fn test_is_git_url() {
+        assert!(is_git_url(
+            "https://github.com/leftwm/leftwm-theme-template"
+        ));
+        assert!(is_git_url("git@github.com:me/template.git"));
+        assert!(is_git_url("file:///tmp/template"));
+        assert!(!is_git_url("basic_polybar"));
+        assert!(!is_git_url("/tmp/template"));
+    }
+
+    #[test]
+    fn test_list_and_find() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let user = tmpdir.path().join(TEMPLATES_DIR);
+        fs::create_dir_all(user.join("minimal")).unwrap();
+        fs::create_dir_all(user.join("bar")).unwrap();
+        fs::write(user.join("not-a-template.txt"), "").unwrap();
+        let installed = tmpdir.path().join("themes").join("installed");
+        fs::create_dir_all(&installed).unwrap();
+
+        let mut config = Config::new(Some(tmpdir.path().to_path_buf())).default();
+        config.repos[0].themes = vec![
+            Theme::new("installed", None, Some(installed.clone())),
+            Theme::new("uninstalled", None, None),
+        ];
+
+        let templates = Template::list(&mut config).unwrap();
+        let user_templates: Vec<&str> = templates
+            .iter()
+            .filter(|t| t.source == TemplateSource::User)
+            .map(|t| t.name.as_str())
+            .collect();
+        assert_eq!(user_templates, vec!["bar", "minimal"]);
+        assert!(templates.iter().any(|t| t.name == "installed"
+            && t.source == TemplateSource::Installed
+            && t.location == installed.display().to_string()));
+        assert!(!templates.iter().any(|t| t.name == "uninstalled"));
+
+        let found = Template::find(&mut config, "minimal").unwrap();
+        assert_eq!(found.location, user.join("minimal").display().to_string());
+        let found = Template::find(&mut config, &installed.display().to_string()).unwrap();
+        assert_eq!(found.source, TemplateSource::Path);
+        assert_eq!(
+            Template::find(&mut config, "https://example.com/t.git")
+                .unwrap()
+                .source,
+            TemplateSource::Git
+        );
+        assert!(Template::find(&mut config, "nonexistent").is_err());
+    }
+
+    #[test]
+    fn test_substitute() {
+        let values = BTreeMap::from([
+            (String::from("name"), String::from("mine")),
+            (String::from("bar"), String::from("polybar")),
+        ]);
+        let (result, unknown) = substitute("{{name}} uses {{ bar }}; {{ font }} {x}", &values);
+        assert_eq!(result, "mine uses polybar; {{ font }} {x}");
+        assert_eq!(unknown, BTreeSet::from([String::from("font")]));
+    }
+
+    #[test]
+    fn test_check_value() {
+        assert!(check_value("#FF0000").is_ok());
+        assert!(check_value("A theme, with spaces & (parens)").is_ok());
+        assert!(check_value("a \"b\"").is_err());
+        assert!(check_value("it's").is_err());
+        assert!(check_value("$(rm -rf ~)").is_err());
+        assert!(check_value("back\\slash").is_err());
+        assert!(check_value("two\nlines").is_err());
+    }
+
+    #[test]
+    fn test_builtin_template() {
+        let template = Template {
+            name: String::from(BUILTIN_TEMPLATE),
+            source: TemplateSource::Builtin,
+            location: String::new(),
+        };
+        let fetched = template.fetch().unwrap();
+        let mode = |name: &str| {
+            fs::metadata(fetched.path.join(name))
+                .unwrap()
+                .permissions()
+                .mode()
+        };
+        assert_eq!(mode("up") & 0o111, 0o111);
+        assert_eq!(mode("down") & 0o111, 0o111);
+        assert_eq!(mode("theme.ron") & 0o111, 0);
+
+        let variables = fetched.variables().unwrap();
+        let names: Vec<&str> = variables.iter().map(|v| v.name.as_str()).collect();
+        for expected in ["author", "bar", "primary_color", "accent_color", "font"] {
+            assert!(names.contains(&expected), "{}", expected);
+        }
+
+        // With every variable filled in, the files are valid.
+        let mut values: BTreeMap<String, String> =
+            variables.into_iter().map(|v| (v.name, v.default)).collect();
+        values.insert(String::from("name"), String::from("mine"));
+        render_dir(&fetched.path, &values).unwrap();
+        let theme = crate::models::ThemeFile::load(&fetched.path.join("theme.ron")).unwrap();
+        assert_eq!(theme.focused_border_color.as_deref(), Some("#FABD2F"));
+        let entry = fs::read_to_string(fetched.path.join("index-entry.toml")).unwrap();
+        let entry: crate::models::theme::TempThemes = toml::from_str(&entry).unwrap();
+        assert_eq!(entry.theme[0].name, "mine");
+        assert_eq!(mode("up") & 0o111, 0o111);
+    }
+
+    #[test]
+    fn test_fetch_git_template() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let origin = tmpdir.path().join("origin");
+        fs::create_dir_all(&origin).unwrap();
+        Repository::init(&origin).unwrap();
+        fs::write(origin.join("up"), "#!/bin/sh").unwrap();
+        crate::utils::git::commit_files(&origin, &[&origin.join("up")], "Template").unwrap();
+
+        let template = Template {
+            name: String::from("origin"),
+            source: TemplateSource::Git,
+            location: format!("file://{}", origin.display()),
+        };
+        let fetched = template.fetch().unwrap();
+        assert!(fetched.path.join("up").exists());
+        let path = fetched.path.clone();
+        drop(fetched);
+        assert!(!path.exists());
+    }
+}
@@ -1,8 +1,18 @@
 use crate::errors;
+use crate::models::config::CURRENT_DIR;
 use crate::models::{Config, THEMES_DIR};
+use crate::operations::New;
+use crate::utils::overrides;
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 
+/// Key of `DependencyL::packages` used for distributions not listed.
+pub const ANY_DISTRIBUTION: &str = "*";
+
+/// File in a theme's repository holding its entry for `known.toml`.
+pub const INDEX_ENTRY_FILE: &str = "index-entry.toml";
+
 /// Contains information about a theme contained within themes.toml (or known.toml upstream).
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Theme {
@@ -28,6 +38,16 @@ pub struct Theme {
     pub relative_directory: Option<String>,
     /// URL to the help resources for this theme
     pub support_url: Option<String>,
+    /// (v2) The branch to follow when upgrading; defaults to master
+    pub branch: Option<String>,
+    /// (v2) Names the theme was previously published under
+    pub renamed_from: Option<Vec<String>>,
+    /// (v2) Why the theme should no longer be used
+    pub deprecated: Option<String>,
+    /// (v2) Name of the theme to use instead
+    pub replaced_by: Option<String>,
+    /// (Local) Whether the theme is no longer in its repository's index
+    pub removed_upstream: Option<bool>,
     #[serde(skip)]
     pub source: Option<String>,
 }
@@ -40,6 +60,17 @@ pub struct TempThemes {
     pub theme: Vec<Theme>,
 }
 
+impl TempThemes {
+    /// Parses a theme index, see `definitions::parse_index`.
+    ///
+    /// # Errors
+    ///
+    /// Will error if the index cannot be parsed.
+    pub fn parse(contents: &str) -> errors::Result<TempThemes> {
+        crate::models::definitions::parse_index(contents)
+    }
+}
+
 impl Default for TempThemes {
     fn default() -> TempThemes {
         TempThemes {
@@ -49,12 +80,14 @@ impl Default for TempThemes {
     }
 }
 
-/// Contains information pertaining to a program dependency (name, required/optional, package).
+/// Contains information pertaining to a program dependency (name, required/optional, packages).
 #[derive(Debug, Deserialize, Clone, Serialize)]
 pub struct DependencyL {
     pub program: String,
     pub optional: Option<bool>,
-    pub package: Option<String>,
+    /// (v2) The package providing the program, by distribution ID as in
+    /// `/etc/os-release`; `*` is used for any other distribution
+    pub packages: Option<BTreeMap<String, String>>,
 }
 
 impl Default for DependencyL {
@@ -62,11 +95,23 @@ impl Default for DependencyL {
         DependencyL {
             program: String::from("leftwm"),
             optional: None,
-            package: None,
+            packages: None,
         }
     }
 }
 
+impl DependencyL {
+    /// Returns the package providing the program on the given distribution.
+    #[must_use]
+    pub fn package_for(&self, distribution: Option<&str>) -> Option<&str> {
+        let packages = self.packages.as_ref()?;
+        distribution
+            .and_then(|distribution| packages.get(distribution))
+            .or_else(|| packages.get(ANY_DISTRIBUTION))
+            .map(String::as_str)
+    }
+}
+
 impl Theme {
     #[must_use]
     pub fn new(name: &str, description: Option<String>, directory: Option<PathBuf>) -> Self {
@@ -82,6 +127,11 @@ impl Theme {
             current: Some(false),
             relative_directory: None,
             support_url: None,
+            branch: None,
+            renamed_from: None,
+            deprecated: None,
+            replaced_by: None,
+            removed_upstream: None,
             source: None,
         }
     }
@@ -110,6 +160,27 @@ impl Theme {
             .cloned()
     }
 
+    /// Finds the metadata of the theme whose repository is checked out at `dir`:
+    /// the installed theme in that directory, or else the entry in the
+    /// directory's `index-entry.toml`.
+    pub fn find_by_directory(config: &mut Config, dir: &Path) -> Option<Theme> {
+        let dir = fs::canonicalize(dir).ok()?;
+        let installed = config.themes(false).into_iter().find(|theme| {
+            theme
+                .directory
+                .as_ref()
+                .and_then(|theme_dir| fs::canonicalize(theme_dir).ok())
+                .is_some_and(|theme_dir| theme_dir == dir)
+        });
+        installed.or_else(|| Theme::from_index_entry(&dir))
+    }
+
+    /// Reads the first entry of the `index-entry.toml` in `dir`, if any.
+    pub fn from_index_entry(dir: &Path) -> Option<Theme> {
+        let contents = fs::read_to_string(dir.join(INDEX_ENTRY_FILE)).ok()?;
+        TempThemes::parse(&contents).ok()?.theme.into_iter().next()
+    }
+
     pub fn find_all(config: &mut Config, name: &str) -> Option<Vec<Theme>> {
         let (themes, _) = config
             .themes(false)
@@ -145,27 +216,76 @@ impl Theme {
         self.relative_directory.clone()
     }
 
+    /// Describes why the theme should no longer be used, if it shouldn't.
+    #[must_use]
+    pub fn notice(&self) -> Option<String> {
+        let mut notes = Vec::new();
+        if self.removed_upstream == Some(true) {
+            notes.push(String::from("removed from its repository"));
+        }
+        if let Some(reason) = &self.deprecated {
+            notes.push(format!("deprecated: {reason}"));
+        }
+        if let Some(other) = &self.replaced_by {
+            notes.push(format!("replaced by {other}"));
+        }
+        if notes.is_empty() {
+            None
+        } else {
+            Some(notes.join("; "))
+        }
+    }
+
     pub fn current(&mut self, currency: bool) {
         self.current = if currency { Some(true) } else { None }
     }
 
-    /// Gets the name of the theme after applying any theme changes.
-    pub fn get_name(&self) -> String {
-        // TODO: When theme rename change is implemented, return the newly
-        // obtained name after applying the rename change if any.
-        self.name.clone()
+    /// Gets the earlier name (see `renamed_from`) under which the theme is
+    /// still installed, if it was renamed upstream. Only directories of
+    /// entries in `owned`, the themes of the theme's own repository, count.
+    pub fn installed_old_name(&self, config_dir: &Path, owned: &[Theme]) -> Option<String> {
+        let themes_dir = config_dir.join(THEMES_DIR);
+        if !Theme::is_dir_name(&self.name) || themes_dir.join(&self.name).exists() {
+            return None;
+        }
+        self.renamed_from
+            .iter()
+            .flatten()
+            .find(|old_name| {
+                let old_dir = themes_dir.join(old_name);
+                *old_name != &self.name
+                    && Theme::is_dir_name(old_name)
+                    && owned.iter().any(|theme| {
+                        (&theme.name == *old_name || theme.name == self.name)
+                            && theme.directory.as_ref() == Some(&old_dir)
+                    })
+                    && fs::symlink_metadata(&old_dir).is_ok_and(|meta| meta.is_dir())
+            })
+            .cloned()
+    }
+
+    // Whether a name from an index can be used as a directory in the themes
+    // directory, so a rename cannot reach outside of it or replace `current`.
+    fn is_dir_name(name: &str) -> bool {
+        !name.is_empty() && name != CURRENT_DIR && New::name_problem(name).is_none()
     }
 
-    /// Applies changes to the repo if changes are defined for it.
+    /// Applies changes to the repo if changes are defined for it. Returns the
+    /// old name if the installed theme was renamed.
     /// # Errors
     ///
     /// Errors if the directory rename change fails.
-    pub fn apply_changes(&self, config_dir: &Path) -> Result<(), errors::LeftError> {
-        let effectual_name = self.get_name();
-        if self.name != effectual_name {
-            Theme::apply_change_rename_dir(&self.name, &effectual_name, config_dir)?;
-        }
-        Ok(())
+    pub fn apply_changes(
+        &self,
+        config_dir: &Path,
+        owned: &[Theme],
+    ) -> Result<Option<String>, errors::LeftError> {
+        let Some(old_name) = self.installed_old_name(config_dir, owned) else {
+            return Ok(None);
+        };
+        Theme::apply_change_rename_dir(&old_name, &self.name, config_dir)?;
+        overrides::rename(config_dir, &old_name, &self.name)?;
+        Ok(Some(old_name))
     }
 
     // Applies the theme rename change. Given an old name and a new name of the
@@ -176,7 +296,7 @@ impl Theme {
         config_dir: &Path,
     ) -> Result<(), errors::LeftError> {
         let old_theme_dir = config_dir.join(THEMES_DIR).join(old_name);
-        if old_theme_dir.exists() {
+        if fs::symlink_metadata(&old_theme_dir).is_ok_and(|meta| meta.is_dir()) {
             println!("Moving theme {old_name} to {new_name}");
             let new_theme_dir = config_dir.join(THEMES_DIR).join(new_name);
             fs::rename(old_theme_dir, new_theme_dir)?;
@@ -216,4 +336,116 @@ mod test {
         assert!(Theme::apply_change_rename_dir("theme-x", "theme-y", &tmpdir.keep()).is_ok());
         assert!(!new_theme_dir.exists());
     }
+
+    #[test]
+    fn test_installed_old_name() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let themes_dir = tmpdir.path().join(THEMES_DIR);
+        let owned = vec![Theme::new(
+            "theme-x",
+            None,
+            Some(themes_dir.join("theme-x")),
+        )];
+        let mut theme = Theme::new("theme-y", None, None);
+        assert_eq!(theme.installed_old_name(tmpdir.path(), &owned), None);
+
+        theme.renamed_from = Some(vec![String::from("theme-w"), String::from("theme-x")]);
+        assert_eq!(theme.installed_old_name(tmpdir.path(), &owned), None);
+
+        fs::create_dir_all(themes_dir.join("theme-x")).unwrap();
+        assert_eq!(
+            theme.installed_old_name(tmpdir.path(), &owned).as_deref(),
+            Some("theme-x")
+        );
+
+        // Nothing to do once the theme is installed under its new name.
+        fs::create_dir_all(themes_dir.join("theme-y")).unwrap();
+        assert_eq!(theme.installed_old_name(tmpdir.path(), &owned), None);
+    }
+
+    #[test]
+    fn test_installed_old_name_ignores_current() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let themes_dir = tmpdir.path().join(THEMES_DIR);
+        fs::create_dir_all(themes_dir.join("theme-x")).unwrap();
+        std::os::unix::fs::symlink(themes_dir.join("theme-x"), themes_dir.join(CURRENT_DIR))
+            .unwrap();
+        let owned = vec![Theme::new(
+            CURRENT_DIR,
+            None,
+            Some(themes_dir.join(CURRENT_DIR)),
+        )];
+        let mut theme = Theme::new("theme-y", None, None);
+        theme.renamed_from = Some(vec![String::from(CURRENT_DIR)]);
+
+        assert_eq!(theme.installed_old_name(tmpdir.path(), &owned), None);
+        assert_eq!(theme.apply_changes(tmpdir.path(), &owned).unwrap(), None);
+        assert!(fs::symlink_metadata(themes_dir.join(CURRENT_DIR))
+            .unwrap()
+            .file_type()
+            .is_symlink());
+    }
+
+    #[test]
+    fn test_installed_old_name_ignores_paths() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let themes_dir = tmpdir.path().join(THEMES_DIR);
+        fs::create_dir_all(tmpdir.path().join("x")).unwrap();
+        fs::create_dir_all(&themes_dir).unwrap();
+        let owned = vec![Theme::new("../x", None, Some(themes_dir.join("../x")))];
+        let mut theme = Theme::new("theme-y", None, None);
+        theme.renamed_from = Some(vec![String::from("../x")]);
+
+        assert_eq!(theme.installed_old_name(tmpdir.path(), &owned), None);
+        assert_eq!(theme.apply_changes(tmpdir.path(), &owned).unwrap(), None);
+        assert!(tmpdir.path().join("x").is_dir());
+        assert!(!themes_dir.join("theme-y").exists());
+    }
+
+    #[test]
+    fn test_installed_old_name_ignores_other_repos() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let themes_dir = tmpdir.path().join(THEMES_DIR);
+        fs::create_dir_all(themes_dir.join("theme-x")).unwrap();
+        // Another repository's (or a local) theme is installed as theme-x,
+        // while this repository only knows it without a directory.
+        let owned = vec![Theme::new("theme-x", None, None)];
+        let mut theme = Theme::new("theme-y", None, None);
+        theme.renamed_from = Some(vec![String::from("theme-x")]);
+
+        assert_eq!(theme.installed_old_name(tmpdir.path(), &owned), None);
+        assert_eq!(theme.installed_old_name(tmpdir.path(), &[]), None);
+        assert_eq!(theme.apply_changes(tmpdir.path(), &owned).unwrap(), None);
+        assert!(themes_dir.join("theme-x").is_dir());
+    }
+
+    #[test]
+    fn test_apply_changes_renames_theme_and_overrides() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let themes_dir = tmpdir.path().join(THEMES_DIR);
+        fs::create_dir_all(themes_dir.join("theme-x")).unwrap();
+        fs::create_dir_all(tmpdir.path().join(overrides::OVERRIDES_DIR)).unwrap();
+        fs::write(overrides::file(tmpdir.path(), "theme-x"), "()").unwrap();
+        let owned = vec![Theme::new(
+            "theme-x",
+            None,
+            Some(themes_dir.join("theme-x")),
+        )];
+        let mut theme = Theme::new("theme-y", None, None);
+        theme.renamed_from = Some(vec![String::from("theme-x")]);
+
+        assert_eq!(
+            theme
+                .apply_changes(tmpdir.path(), &owned)
+                .unwrap()
+                .as_deref(),
+            Some("theme-x")
+        );
+        assert!(themes_dir.join("theme-y").is_dir());
+        assert!(!themes_dir.join("theme-x").exists());
+        assert!(overrides::file(tmpdir.path(), "theme-y").exists());
+        assert!(!overrides::file(tmpdir.path(), "theme-x").exists());
+
+        assert_eq!(theme.apply_changes(tmpdir.path(), &owned).unwrap(), None);
+    }
 }
@@ -0,0 +1,416 @@
+//! The `theme.ron` / `theme.toml` settings file read by `LeftWM`.
+use crate::errors::{LeftError, Result};
+use leftwm_core::models::Gutter;
+use log::warn;
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Names of the fields `LeftWM` understands in a theme file, as serde reads
+/// them into [`ThemeFile`], so the two cannot drift apart.
+pub fn fields() -> &'static [&'static str] {
+    static FIELDS: OnceLock<&'static [&'static str]> = OnceLock::new();
+    FIELDS.get_or_init(|| {
+        let mut names: &'static [&'static str] = &[];
+        let _ = ThemeFile::deserialize(FieldNames(&mut names));
+        names
+    })
+}
+
+// A deserializer which only records the field names a struct asks for.
+struct FieldNames<'a>(&'a mut &'static [&'static str]);
+
+impl<'de> de::Deserializer<'de> for FieldNames<'_> {
+    type Error = de::value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(
+        self,
+        _visitor: V,
+    ) -> std::result::Result<V::Value, Self::Error> {
+        Err(de::Error::custom("only structs have field names"))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        _visitor: V,
+    ) -> std::result::Result<V::Value, Self::Error> {
+        *self.0 = fields;
+        Err(de::Error::custom("field names recorded"))
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map enum identifier ignored_any
+    }
+}
+
+// Calls `$f` with a reference to the known field named `$field`, plus `$arg`s.
+macro_rules! with_field {
+    ($theme:expr, $field:expr, $f:ident($($arg:expr),*)) => {
+        match $field {
+            "border_width" => $f($theme.border_width.as_ref() $(, $arg)*),
+            "margin" => $f($theme.margin.as_ref() $(, $arg)*),
+            "workspace_margin" => $f($theme.workspace_margin.as_ref() $(, $arg)*),
+            "default_width" => $f($theme.default_width.as_ref() $(, $arg)*),
+            "default_height" => $f($theme.default_height.as_ref() $(, $arg)*),
+            "always_float" => $f($theme.always_float.as_ref() $(, $arg)*),
+            "gutter" => $f($theme.gutter.as_ref() $(, $arg)*),
+            "default_border_color" => $f($theme.default_border_color.as_ref() $(, $arg)*),
+            "floating_border_color" => $f($theme.floating_border_color.as_ref() $(, $arg)*),
+            "focused_border_color" => $f($theme.focused_border_color.as_ref() $(, $arg)*),
+            "background_color" => $f($theme.background_color.as_ref() $(, $arg)*),
+            "on_new_window" => $f($theme.on_new_window_cmd.as_ref() $(, $arg)*),
+            other => Err(LeftError::from(format!("Unknown theme field {other}").as_str())),
+        }
+    };
+}
+
+/// Settings of a theme, as read by `LeftWM` from `theme.ron` or `theme.toml`.
+///
+/// This mirrors `ThemeSetting` of the `leftwm` crate, which is not published as
+/// a library; the values use the `leftwm-core` types where it has them. A
+/// field added here is picked up by [`fields`], and a test checks that the
+/// accessors below handle every one of them.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct ThemeFile {
+    pub border_width: Option<i32>,
+    pub margin: Option<CustomMargins>,
+    pub workspace_margin: Option<CustomMargins>,
+    pub default_width: Option<i32>,
+    pub default_height: Option<i32>,
+    pub always_float: Option<bool>,
+    pub gutter: Option<Vec<Gutter>>,
+    pub default_border_color: Option<String>,
+    pub floating_border_color: Option<String>,
+    pub focused_border_color: Option<String>,
+    pub background_color: Option<String>,
+    #[serde(rename = "on_new_window")]
+    pub on_new_window_cmd: Option<String>,
+    /// Fields not known to leftwm-theme, kept so they survive conversions
+    #[serde(skip)]
+    pub extra: BTreeMap<String, ron::Value>,
+}
+
+/// A margin, either one value for all sides or `[top, right, bottom, left]` as per HTML.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum CustomMargins {
+    Int(u32),
+    Vec(Vec<u32>),
+}
+
+impl ThemeFile {
+    /// Loads a theme file, choosing the format by the file extension.
+    ///
+    /// # Errors
+    ///
+    /// Will error if the file cannot be read or does not parse.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        if path.extension().is_some_and(|ext| ext == "ron") {
+            ThemeFile::from_ron_str(&contents)
+        } else {
+            ThemeFile::from_toml_str(&contents)
+        }
+    }
+
+    /// # Errors
+    ///
+    /// Will error if the contents are not a valid `theme.toml`.
+    pub fn from_toml_str(contents: &str) -> Result<Self> {
+        let mut theme: ThemeFile = toml::from_str(contents)?;
+        let table: toml::Table = toml::from_str(contents)?;
+        for (key, value) in table {
+            if !fields().contains(&key.as_str()) {
+                warn!("Unknown field `{key}` in theme file, keeping it as-is");
+                theme.extra.insert(key, ron::Value::deserialize(value)?);
+            }
+        }
+        Ok(theme)
+    }
+
+    /// # Errors
+    ///
+    /// Will error if the contents are not a valid `theme.ron`.
+    pub fn from_ron_str(contents: &str) -> Result<Self> {
+        let mut theme: ThemeFile = ron::from_str(contents)?;
+        if let ron::Value::Map(map) = ron::from_str::<ron::Value>(contents)? {
+            for (key, value) in map {
+                let ron::Value::String(key) = key else {
+                    continue;
+                };
+                if !fields().contains(&key.as_str()) {
+                    warn!("Unknown field `{key}` in theme file, keeping it as-is");
+                    theme.extra.insert(key, value);
+                }
+            }
+        }
+        Ok(theme)
+    }
+
+    /// Serializes the theme in the format written by `leftwm-theme migrate`.
+    ///
+    /// # Errors
+    ///
+    /// Will error if a value cannot be represented in RON.
+    pub fn to_ron_string(&self) -> Result<String> {
+        let ron_pretty_conf = ron::ser::PrettyConfig::new()
+            .depth_limit(2)
+            .extensions(ron::extensions::Extensions::IMPLICIT_SOME);
+        let mut ron_theme = ron::ser::to_string_pretty(&self, ron_pretty_conf)?;
+        if self.extra.is_empty() {
+            return Ok(ron_theme);
+        }
+
+        // serde would write flattened fields as a map, which `LeftWM` cannot
+        // read, so unknown fields are spliced into the struct by hand.
+        let closing = ron_theme
+            .rfind(')')
+            .ok_or_else(|| LeftError::from("Unexpected RON output"))?;
+        ron_theme.truncate(closing);
+        for (key, value) in &self.extra {
+            if !is_ron_identifier(key) {
+                warn!("Field `{key}` cannot be written to RON, dropping it");
+                continue;
+            }
+            let value = ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::new())?;
+            writeln!(ron_theme, "    {key}: {},", value.replace('\n', "\n    "))
+                .map_err(|_| LeftError::from("Unexpected RON output"))?;
+        }
+        ron_theme.push(')');
+        Ok(ron_theme)
+    }
+
+    /// Serializes the theme as `theme.toml`, for versions of `LeftWM` before `theme.ron`.
+    ///
+    /// # Errors
+    ///
+    /// Will error if a value cannot be represented in TOML.
+    pub fn to_toml_string(&self) -> Result<String> {
+        let mut extra = toml::Table::new();
+        for (key, value) in &self.extra {
+            match toml::Value::try_from(value) {
+                Ok(value) => {
+                    extra.insert(key.clone(), value);
+                }
+                Err(err) => warn!("Field `{key}` cannot be written to TOML ({err}), dropping it"),
+            }
+        }
+        Ok(toml::to_string(&TomlThemeFile { theme: self, extra })?)
+    }
+}
+
+impl ThemeFile {
+    /// Lays `overrides` over these settings: every field set there, known or
+    /// not, replaces the one here.
+    pub fn merge(&mut self, overrides: &ThemeFile) {
+        macro_rules! merge_fields {
+            ($($field:ident),*) => {
+                $(
+                    if overrides.$field.is_some() {
+                        self.$field.clone_from(&overrides.$field);
+                    }
+                )*
+            };
+        }
+        merge_fields!(
+            border_width,
+            margin,
+            workspace_margin,
+            default_width,
+            default_height,
+            always_float,
+            gutter,
+            default_border_color,
+            floating_border_color,
+            focused_border_color,
+            background_color,
+            on_new_window_cmd
+        );
+        for (key, value) in &overrides.extra {
+            self.extra.insert(key.clone(), value.clone());
+        }
+    }
+
+    /// Serializes a single known field as a RON literal. With `implicit_some`,
+    /// as enabled in files written by `migrate`, values are not wrapped in `Some(..)`.
+    ///
+    /// # Errors
+    ///
+    /// Will error if the field is unknown or cannot be represented in RON.
+    pub fn field_to_ron(&self, field: &str, implicit_some: bool) -> Result<String> {
+        with_field!(self, field, ron_literal(implicit_some))
+    }
+
+    /// Serializes a single known field as a TOML literal.
+    ///
+    /// # Errors
+    ///
+    /// Will error if the field is unknown, unset or cannot be represented in TOML.
+    pub fn field_to_toml(&self, field: &str) -> Result<String> {
+        with_field!(self, field, toml_literal())
+    }
+}
+
+fn ron_literal<T: Serialize>(value: Option<&T>, implicit_some: bool) -> Result<String> {
+    Ok(match value {
+        None => String::from("None"),
+        Some(value) if implicit_some => ron::to_string(value)?,
+        Some(value) => format!("Some({})", ron::to_string(value)?),
+    })
+}
+
+fn toml_literal<T: Serialize>(value: Option<&T>) -> Result<String> {
+    let value = value.ok_or_else(|| LeftError::from("TOML cannot represent an unset value"))?;
+    Ok(toml::Value::try_from(value)?.to_string())
+}
+
+// TOML has no struct syntax, so unknown fields can simply be flattened in.
+#[derive(Serialize)]
+struct TomlThemeFile<'a> {
+    #[serde(flatten)]
+    theme: &'a ThemeFile,
+    #[serde(flatten)]
+    extra: toml::Table,
+}
+
+fn is_ron_identifier(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_unknown_toml_fields_are_kept() {
+        let theme = ThemeFile::from_toml_str(
+            "border_width = 2\nbar_height = 24\n[tags]\nlabels = [\"1\", \"2\"]\n",
+        )
+        .unwrap();
+        assert_eq!(theme.border_width, Some(2));
+        assert_eq!(theme.extra.len(), 2);
+        assert!(theme.extra.contains_key("bar_height"));
+        assert!(theme.extra.contains_key("tags"));
+
+        let ron_theme = theme.to_ron_string().unwrap();
+        let reread = ThemeFile::from_ron_str(&ron_theme).unwrap();
+        assert_eq!(reread.border_width, Some(2));
+        assert_eq!(reread.extra.len(), 2);
+    }
+
+    #[test]
+    fn test_unknown_ron_fields_are_kept() {
+        let theme = ThemeFile::from_ron_str(
+            "#![enable(implicit_some)]\n(border_width: 1, margin: [1, 2, 3, 4], bar_height: 24)",
+        )
+        .unwrap();
+        assert_eq!(theme.margin, Some(CustomMargins::Vec(vec![1, 2, 3, 4])));
+        assert_eq!(
+            theme.extra.get("bar_height"),
+            Some(&ron::Value::Number(ron::Number::U8(24)))
+        );
+    }
+
+    #[test]
+    fn test_margins_to_toml() {
+        let theme = ThemeFile {
+            margin: Some(CustomMargins::Int(5)),
+            workspace_margin: Some(CustomMargins::Vec(vec![1, 2, 3, 4])),
+            ..ThemeFile::default()
+        };
+        let toml_theme = theme.to_toml_string().unwrap();
+        assert_eq!(toml_theme, "margin = 5\nworkspace_margin = [1, 2, 3, 4]\n");
+        assert_eq!(ThemeFile::from_toml_str(&toml_theme).unwrap(), theme);
+    }
+
+    #[test]
+    fn test_unrepresentable_fields_are_dropped_from_toml() {
+        let theme = ThemeFile::from_ron_str(
+            "#![enable(implicit_some)]\n(border_width: 1, layout: Monocle, bar_height: 24)",
+        )
+        .unwrap();
+        assert_eq!(
+            theme.to_toml_string().unwrap(),
+            "border_width = 1\nbar_height = 24\n"
+        );
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut theme = ThemeFile::from_ron_str(
+            "(border_width: Some(1), margin: Some(5), focused_border_color: Some(\"#FFFFFF\"), bar_height: 22)",
+        )
+        .unwrap();
+        let overrides = ThemeFile::from_ron_str(
+            "#![enable(implicit_some)]\n(focused_border_color: \"#FF0000\", margin: [1, 2, 3, 4], bar_height: 30)",
+        )
+        .unwrap();
+        theme.merge(&overrides);
+        assert_eq!(theme.border_width, Some(1));
+        assert_eq!(theme.margin, Some(CustomMargins::Vec(vec![1, 2, 3, 4])));
+        assert_eq!(theme.focused_border_color.as_deref(), Some("#FF0000"));
+        assert_eq!(
+            theme.extra.get("bar_height"),
+            overrides.extra.get("bar_height")
+        );
+    }
+
+    #[test]
+    fn test_field_literals() {
+        let theme = ThemeFile::from_ron_str(
+            "#![enable(implicit_some)]\n(border_width: 2, gutter: [(side: Top, value: 24, id: None)])",
+        )
+        .unwrap();
+        assert_eq!(theme.field_to_ron("border_width", true).unwrap(), "2");
+        assert_eq!(
+            theme.field_to_ron("border_width", false).unwrap(),
+            "Some(2)"
+        );
+        assert_eq!(theme.field_to_ron("margin", true).unwrap(), "None");
+        assert_eq!(
+            theme.field_to_ron("gutter", true).unwrap(),
+            "[(side:Top,value:24,id:None)]"
+        );
+        assert_eq!(
+            theme.field_to_toml("gutter").unwrap(),
+            "[{ side = \"Top\", value = 24 }]"
+        );
+        assert!(theme.field_to_toml("margin").is_err());
+        assert!(theme.field_to_ron("bar_height", true).is_err());
+    }
+
+    #[test]
+    fn test_every_field_is_handled() {
+        assert_eq!(fields().len(), 12);
+        assert!(fields().contains(&"on_new_window"));
+        let full = ThemeFile::from_ron_str(
+            "#![enable(implicit_some)]\n(border_width: 1, margin: 2, workspace_margin: [1, 2, 3, 4], default_width: 800, default_height: 600, always_float: true, gutter: [(side: Top, value: 24, id: None)], default_border_color: \"#000000\", floating_border_color: \"#111111\", focused_border_color: \"#FFFFFF\", background_color: \"#222222\", on_new_window: \"echo new\")",
+        )
+        .unwrap();
+        assert!(full.extra.is_empty());
+        for field in fields() {
+            assert_ne!(full.field_to_ron(field, true).unwrap(), "None", "{field}");
+        }
+        let mut merged = ThemeFile::default();
+        merged.merge(&full);
+        assert_eq!(merged, full);
+    }
+
+    #[test]
+    fn test_invalid_fields_are_errors() {
+        assert!(ThemeFile::from_toml_str("border_width = \"wide\"").is_err());
+        assert!(ThemeFile::from_ron_str("(margin: \"wide\")").is_err());
+    }
+}
@@ -0,0 +1,151 @@
+use crate::errors::{friendly_message, Result};
+use crate::models::config::LOCAL_REPO_NAME;
+use crate::models::LockFile;
+use std::fs;
+use std::path::Path;
+
+/// The default name of the manifest of wanted themes, in the config directory.
+pub const WANTED_FILE: &str = "themes-wanted.toml";
+
+/// The themes a user wants installed, read from a manifest they maintain
+/// (e.g. in their dotfiles) or from a lock file, and reconciled by
+/// `leftwm-theme sync`.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct Wanted {
+    /// Name of the theme to apply
+    pub current: Option<String>,
+    /// Keep installed themes which are not listed, instead of uninstalling them
+    #[serde(default)]
+    pub keep_unlisted: bool,
+    /// Repositories (known.toml) to add to themes.toml
+    #[serde(default)]
+    pub repos: Vec<WantedRepo>,
+    #[serde(default)]
+    pub theme: Vec<WantedTheme>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WantedRepo {
+    pub name: String,
+    pub url: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WantedTheme {
+    pub name: String,
+    /// Repository listing the theme, when several list one with this name
+    pub repo: Option<String>,
+    /// Commit to pin the theme to; otherwise it follows its repository
+    pub commit: Option<String>,
+    /// git repository to clone the theme from, for themes in no repository;
+    /// needs a `commit`
+    pub repository: Option<String>,
+    /// Path to the directory containing up, down, and theme.toml w.r.t. root
+    pub relative_directory: Option<String>,
+}
+
+impl Wanted {
+    /// Reads a manifest, or a lock file if the path ends in `.lock`.
+    ///
+    /// # Errors
+    ///
+    /// Will error if the file cannot be read or parsed.
+    /// Will error if a theme has a `repository` but no `commit`.
+    pub fn load(path: &Path) -> Result<Wanted> {
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "lock")
+        {
+            return Ok(Wanted::from(LockFile::load(path)?));
+        }
+        let contents = fs::read_to_string(path).map_err(|err| {
+            friendly_message(&format!("Could not read {}: {err}", path.display()))
+        })?;
+        let wanted: Wanted = toml::from_str(&contents)?;
+        if let Some(theme) = wanted
+            .theme
+            .iter()
+            .find(|theme| theme.repository.is_some() && theme.commit.is_none())
+        {
+            return Err(friendly_message(&format!(
+                "{} has a repository but no commit; themes outside a repository must be pinned.",
+                theme.name
+            )));
+        }
+        Ok(wanted)
+    }
+}
+
+impl From<LockFile> for Wanted {
+    fn from(lock: LockFile) -> Self {
+        let mut repos: Vec<WantedRepo> = Vec::new();
+        for locked in &lock.theme {
+            if locked.repo != LOCAL_REPO_NAME && !repos.iter().any(|repo| repo.name == locked.repo)
+            {
+                repos.push(WantedRepo {
+                    name: locked.repo.clone(),
+                    url: locked.repo_url.clone(),
+                });
+            }
+        }
+        Wanted {
+            current: lock.current,
+            keep_unlisted: true,
+            repos,
+            theme: lock
+                .theme
+                .into_iter()
+                .map(|locked| WantedTheme {
+                    name: locked.name,
+                    repo: Some(locked.repo),
+                    commit: Some(locked.commit),
+                    repository: Some(locked.repository),
+                    relative_directory: locked.relative_directory,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_load() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let path = tmpdir.path().join(WANTED_FILE);
+        fs::write(
+            &path,
+            r#"
+current = "forest"
+
+[[repos]]
+name = "mine"
+url = "https://example.com/known.toml"
+
+[[theme]]
+name = "forest"
+
+[[theme]]
+name = "ocean"
+repo = "mine"
+commit = "0123456789abcdef0123456789abcdef01234567"
+"#,
+        )
+        .unwrap();
+        let wanted = Wanted::load(&path).unwrap();
+        assert_eq!(wanted.current.as_deref(), Some("forest"));
+        assert!(!wanted.keep_unlisted);
+        assert_eq!(wanted.repos[0].name, "mine");
+        assert_eq!(wanted.theme.len(), 2);
+        assert_eq!(wanted.theme[1].repo.as_deref(), Some("mine"));
+
+        fs::write(
+            &path,
+            "[[theme]]\nname = \"forest\"\nrepository = \"https://github.com/me/forest\"\n",
+        )
+        .unwrap();
+        assert!(Wanted::load(&path).is_err());
+    }
+}
@@ -0,0 +1,110 @@
+use crate::errors::{friendly_message, LeftError};
+use crate::models::config::LOCAL_REPO_NAME;
+use crate::models::{Config, Theme};
+use clap::Parser;
+use colored::Colorize;
+
+/* This function moves installed themes which were removed from their
+   repository's index to the LOCAL repository, so they are kept as local themes.
+*/
+
+#[derive(Parser, Debug)]
+pub struct Adopt {
+    /// Name of an installed theme removed from its repository
+    #[clap(required_unless_present = "all")]
+    pub name: Option<String>,
+
+    /// Adopt every installed theme removed from its repository
+    #[clap(short = 'a', long, conflicts_with = "name")]
+    pub all: bool,
+}
+
+impl Adopt {
+    /// # Errors
+    ///
+    /// Will error if the theme is not installed or still in its repository.
+    /// Will error if config cannot be saved.
+    pub fn exec(&self, config: &mut Config) -> Result<(), LeftError> {
+        let adopted = adopt(config, self.name.as_deref());
+        if adopted.is_empty() {
+            return Err(friendly_message(&match &self.name {
+                Some(name) => {
+                    format!("{name} is not an installed theme removed from its repository.")
+                }
+                None => String::from("No installed themes were removed from their repository."),
+            }));
+        }
+        for name in &adopted {
+            println!(
+                "{}{}{}",
+                "Moved ".bright_blue().bold(),
+                name.bright_green().bold(),
+                " to the LOCAL repository.".bright_blue().bold()
+            );
+        }
+        Config::save(config)?;
+        Ok(())
+    }
+}
+
+// Moves the installed themes removed upstream, all or the one named, to LOCAL.
+// Returns the names of the themes moved.
+fn adopt(config: &mut Config, name: Option<&str>) -> Vec<String> {
+    let mut adopted: Vec<Theme> = Vec::new();
+    for repo in config
+        .repos
+        .iter_mut()
+        .filter(|repo| repo.name != LOCAL_REPO_NAME)
+    {
+        repo.themes.retain(|theme| {
+            let wanted = theme.removed_upstream == Some(true)
+                && theme.directory.is_some()
+                && name.iter().all(|name| *name == theme.name);
+            if wanted {
+                adopted.push(theme.clone());
+            }
+            !wanted
+        });
+    }
+    for theme in &mut adopted {
+        theme.removed_upstream = None;
+        Config::update_or_append(
+            config,
+            theme,
+            (&String::from("localhost"), &String::from(LOCAL_REPO_NAME)),
+        );
+    }
+    adopted.into_iter().map(|theme| theme.name).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::models::Repo;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_adopt() {
+        let mut removed = Theme::new("forest", None, Some(PathBuf::from("/themes/forest")));
+        removed.removed_upstream = Some(true);
+        let present = Theme::new("ocean", None, Some(PathBuf::from("/themes/ocean")));
+        let mut config = Config::new(None).default();
+        config.repos[0].themes = vec![removed, present];
+
+        assert!(adopt(&mut config, Some("ocean")).is_empty());
+        assert_eq!(adopt(&mut config, None), vec!["forest"]);
+
+        let names = |repo: &Repo| -> Vec<String> {
+            repo.themes.iter().map(|theme| theme.name.clone()).collect()
+        };
+        assert_eq!(names(&config.repos[0]), vec!["ocean"]);
+        let local = config
+            .repos
+            .iter()
+            .find(|repo| repo.name == LOCAL_REPO_NAME)
+            .unwrap();
+        assert_eq!(names(local), vec!["forest"]);
+        assert_eq!(local.themes[0].removed_upstream, None);
+        assert!(local.themes[0].directory.is_some());
+    }
+}
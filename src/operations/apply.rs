@@ -1,4 +1,7 @@
-use crate::models::{Config, Theme};
+use crate::models::config::CURRENT_DIR;
+use crate::models::{Config, LeftWm, Theme};
+use crate::utils::overrides;
+use crate::utils::versions::Compatibility;
 use crate::{errors, utils};
 use clap::Parser;
 use colored::Colorize;
@@ -8,7 +11,6 @@ use std::os::unix;
 use std::path::Path;
 use std::process::Command;
 use std::{env, fs};
-use xdg::BaseDirectories;
 
 /* This function sets a particular theme as the current theme in ~./config/leftwm/themes/
      Required args include "THEME", which defines the NAME of a theme as defined in a known.toml file or the themes.toml file in ~/.config/leftwm/
@@ -35,6 +37,7 @@ impl Apply {
     /// Returns an error if config cannot be loaded / saved
     /// Returns an error if `BaseDirectory` not set.
     /// Returns an error if symlink cannot be made.
+    /// Returns an error if the theme's overrides cannot be merged.
     /// Returns an error if theme not found.
     /// Returns an error if leftwm-worker cannot be killed.
     pub fn exec(&self, config: &mut Config) -> Result<(), errors::LeftError> {
@@ -45,9 +48,7 @@ impl Apply {
             &self.name.bright_green().bold(),
             " as default theme.".bright_blue().bold()
         );
-        let mut dir = BaseDirectories::with_prefix("leftwm").create_config_directory("")?;
-        dir.push("themes");
-        dir.push("current");
+        let dir = utils::dir::theme(config)?.join(CURRENT_DIR);
         trace!("{}", &dir.display());
         if let Some(theme) = Theme::find(config, &self.name) {
             if let Some(theme_dir) = theme.directory.as_ref() {
@@ -64,6 +65,15 @@ impl Apply {
                     path.push(rel_dir);
                 }
                 trace!("{}", &path.display());
+                let config_dir = config.get_config_dir()?;
+                let path = overrides::resolve(&config_dir, &theme.name, &path)?;
+                if overrides::file(&config_dir, &theme.name).exists() {
+                    println!(
+                        "{}{}",
+                        "Applying your overrides from ".bright_blue().bold(),
+                        overrides::file(&config_dir, &theme.name).display()
+                    );
+                }
                 match fs::remove_dir_all(&dir) {
                     Ok(()) => {
                         warn!("Removed old current directory");
@@ -123,29 +133,56 @@ pub(crate) fn checks(theme: &Theme) -> bool {
             trace!("No dependencies detected");
         }
         Some(theme_dependencies) => {
+            let distribution = distribution();
             for dependency in theme_dependencies {
                 if !is_program_in_path(&dependency.program) {
+                    match dependency.package_for(distribution.as_deref()) {
+                        Some(package) => error!(
+                            "{} is not installed. It is provided by the package {package}.",
+                            &dependency.program
+                        ),
+                        None => error!("{} is not installed.", &dependency.program),
+                    }
                     return false;
                 }
             }
         }
     }
     trace!("Checking LeftWM version.");
-    if let Ok(true) = utils::versions::check(
+    match utils::versions::check(
         &theme
             .leftwm_versions
             .clone()
             .unwrap_or_else(|| "*".to_string()),
     ) {
-        true
-    } else {
-        error!(
-            "This theme is incompatible with the installed version of LeftWM. \n You may be able to recover this theme, see https://github.com/leftwm/leftwm/wiki/Diagnosing-Theme-Errors"
-        );
-        false
+        Ok(Compatibility::Compatible) => true,
+        Ok(Compatibility::Unknown) => {
+            warn!(
+                "Could not determine the installed version of LeftWM; skipping the version check."
+            );
+            true
+        }
+        _ => {
+            error!(
+                "This theme is incompatible with the installed version of LeftWM. \n You may be able to recover this theme, see https://github.com/leftwm/leftwm/wiki/Diagnosing-Theme-Errors"
+            );
+            if let Some(note) = LeftWm::get().assumed_note() {
+                error!("{note}");
+            }
+            false
+        }
     }
 }
 
+// The ID of the running distribution, from /etc/os-release.
+fn distribution() -> Option<String> {
+    let os_release = fs::read_to_string("/etc/os-release").ok()?;
+    os_release.lines().find_map(|line| {
+        line.strip_prefix("ID=")
+            .map(|id| id.trim_matches('"').to_string())
+    })
+}
+
 fn is_program_in_path(program: &str) -> bool {
     trace!("Checking dependency {program}");
     if let Ok(path) = env::var("PATH") {
@@ -1,50 +1,453 @@
 use crate::errors;
 use crate::errors::friendly_message;
-use crate::models::Config;
-use clap::Parser;
+use crate::models::config::CURRENT_DIR;
+use crate::models::theme_file::fields;
+use crate::models::{Config, Theme, ThemeFile, THEMES_DIR};
+use crate::utils::{edit, overrides};
+use clap::{Parser, Subcommand};
+use colored::Colorize;
+use serde_json::Value;
 use std::fs;
-use std::path::PathBuf;
-use toml::Value;
+use std::os::unix::fs as unix_fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// theme.ron is preferred by LeftWM when both exist.
+const THEME_FILES: &[&str] = &["theme.ron", "theme.toml"];
 
 #[derive(Parser, Debug)]
+#[clap(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct Current {
-    pub field: String,
+    #[clap(subcommand)]
+    pub action: Option<CurrentAction>,
+
+    /// Field to print; use dots for nested values, e.g. `gutter.0.value`
+    #[clap(required = true)]
+    pub field: Option<String>,
+
+    /// Print the value as JSON
+    #[clap(short = 'j', long)]
+    pub json: bool,
 }
 
 impl Current {
     /// # Errors
     ///
-    /// Will error if the requested field in not found in theme.toml
-    ///
-    /// # Panics
+    /// Will error if no theme is currently applied.
+    /// Will error if the current theme has no theme.ron or theme.toml, or it cannot be parsed.
+    /// Will error if the requested field in not found in the theme file.
+    /// Will error if a value being set does not fit the field.
+    pub fn exec(&self, config: &mut Config) -> Result<(), errors::LeftError> {
+        if let Some(CurrentAction::Set(set)) = &self.action {
+            return set.exec(config);
+        }
+        let Some(field) = &self.field else {
+            return Err(friendly_message("No field given."));
+        };
+        let value = read_field(config, field)?;
+        if self.json {
+            println!("{value}");
+        } else {
+            match value {
+                Value::String(s) => println!("{s}"),
+                other => println!("{other}"),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CurrentAction {
+    /// Change a setting of the current theme, keeping the rest of the file as is
+    Set(Set),
+}
+
+#[derive(Parser, Debug)]
+pub struct Set {
+    /// Field to change, e.g. `border_width`
+    pub field: String,
+
+    /// New value; lists and gutters are written as JSON, e.g. `[5, 5, 5, 5]`
+    pub value: String,
+
+    /// Don't reset leftwm-worker
+    #[clap(short = 'n', long)]
+    pub no_reset: bool,
+}
+
+impl Set {
+    /// # Errors
     ///
-    /// Will panic if unable to retrieve value but file does exist.
-    /// Will panic if a current theme is set but directory is not set.
+    /// Will error if the field is unknown or the value does not fit it.
+    /// Will error if the current theme file cannot be read, parsed or written.
     pub fn exec(&self, config: &mut Config) -> Result<(), errors::LeftError> {
-        // define directory so it can be used outside the scope of the loop
-        let mut directory: PathBuf = PathBuf::new();
-
-        // get the path to the current theme.toml
-        for theme in config.themes(false) {
-            if theme.current == Some(true) {
-                directory = theme.directory.unwrap();
-                directory.push("theme");
-                directory.set_extension("toml");
+        // With overrides in place, the current theme is a generated copy; edit
+        // the overrides and regenerate it instead.
+        let config_dir = config.get_config_dir()?;
+        let path = match current_theme(config) {
+            Some(theme) if overrides::file(&config_dir, &theme.name).exists() => {
+                let path = overrides::file(&config_dir, &theme.name);
+                set_field(&path, &self.field, &self.value)?;
+                let generated =
+                    overrides::resolve(&config_dir, &theme.name, &installed_dir(theme)?)?;
+                point_current(&config_dir, &generated)?;
+                path
+            }
+            _ => {
+                let path = current_theme_file(config)?;
+                set_field(&path, &self.field, &self.value)?;
+                path
             }
+        };
+        println!(
+            "{}",
+            format!(
+                "Set {} to {} in {}.",
+                self.field,
+                self.value,
+                path.display()
+            )
+            .bright_blue()
+            .bold()
+        );
+        if !self.no_reset {
+            println!("{}", "Reloading LeftWM.".bright_blue().bold());
+            Command::new("leftwm-command").arg("SoftReload").output()?;
         }
+        Ok(())
+    }
+}
 
-        // read the current theme.toml
-        let file_data: String = fs::read_to_string(directory).unwrap();
-        let cfg_data: Value = toml::from_str(&file_data).expect("no data");
+// Sets a top-level field of a theme file, editing only that field's value.
+fn set_field(path: &Path, field: &str, raw: &str) -> Result<(), errors::LeftError> {
+    if !fields().contains(&field) {
+        return Err(friendly_message(&format!(
+            "Unknown field {field}. Known fields are: {}",
+            fields().join(", ")
+        )));
+    }
+    let contents = fs::read_to_string(path)?;
+    let is_ron = path.extension().is_some_and(|ext| ext == "ron");
+    let theme = ThemeFile::load(path)
+        .map_err(|err| friendly_message(&format!("Could not parse {}: {err}", path.display())))?;
+    let updated = with_value(&theme, field, raw)?;
 
-        // check if the field exists if it doesn't return an error
-        if let Some(field_value) = cfg_data.get(self.field.clone()) {
-            //return requested field
-            println!("{}", format!("{field_value}").replace('\"', ""));
-            Ok(())
-        } else {
-            // returning an error
-            Err(friendly_message("That field was not found"))
+    let edited = if is_ron {
+        let literal = updated.field_to_ron(field, contents.contains("implicit_some"))?;
+        edit::ron_set(&contents, field, &literal)?
+    } else {
+        edit::toml_set(&contents, field, &updated.field_to_toml(field)?)
+    };
+
+    // Make sure the edit produced the intended file before writing it.
+    let reparsed = if is_ron {
+        ThemeFile::from_ron_str(&edited)
+    } else {
+        ThemeFile::from_toml_str(&edited)
+    }
+    .map_err(|err| friendly_message(&format!("Could not update {}: {err}", path.display())))?;
+    if lookup(&theme_value(&reparsed)?, field) != lookup(&theme_value(&updated)?, field) {
+        return Err(friendly_message(&format!(
+            "Could not update {field} in {}",
+            path.display()
+        )));
+    }
+    fs::write(path, edited)?;
+    Ok(())
+}
+
+// Returns a copy of the theme with `field` set to `raw`, read as JSON or else
+// as a plain string, and checked against the theme file schema.
+fn with_value(theme: &ThemeFile, field: &str, raw: &str) -> Result<ThemeFile, errors::LeftError> {
+    let with = |value: Value| -> Result<ThemeFile, serde_json::Error> {
+        let mut settings = serde_json::to_value(theme)?;
+        if let Value::Object(map) = &mut settings {
+            map.insert(field.to_string(), value);
         }
+        serde_json::from_value(settings)
+    };
+    let result = match serde_json::from_str::<Value>(raw) {
+        Ok(value @ (Value::String(_) | Value::Null)) => with(value),
+        Ok(value) => with(value).or_else(|_| with(Value::from(raw))),
+        Err(_) => with(Value::from(raw)),
+    };
+    result.map_err(|err| friendly_message(&format!("Invalid value for {field}: {err}")))
+}
+
+// Reads a (dotted) field from the current theme's settings.
+fn read_field(config: &Config, field: &str) -> Result<Value, errors::LeftError> {
+    let path = current_theme_file(config)?;
+    let theme = ThemeFile::load(&path)
+        .map_err(|err| friendly_message(&format!("Could not parse {}: {err}", path.display())))?;
+    let value = theme_value(&theme)?;
+    match lookup(&value, field) {
+        Some(Value::Null) => Err(friendly_message(&format!(
+            "{field} is not set in the current theme"
+        ))),
+        Some(field_value) => Ok(field_value.clone()),
+        None => Err(friendly_message("That field was not found")),
+    }
+}
+
+/// Returns the settings file (theme.ron or theme.toml) of the active theme.
+///
+/// The `current` symlink is followed, so `relative_directory` has already been
+/// applied to it; if it is missing, the theme marked current in themes.toml is used.
+///
+/// # Errors
+///
+/// Will error if no theme is currently applied or it has no settings file.
+pub(crate) fn current_theme_file(config: &Config) -> Result<PathBuf, errors::LeftError> {
+    let current = config.get_config_dir()?.join(THEMES_DIR).join(CURRENT_DIR);
+    let dir = if current.is_dir() {
+        current
+    } else {
+        let theme = current_theme(config).ok_or_else(|| {
+            friendly_message(
+                "No theme is currently applied. Try `leftwm-theme apply <theme>` first.",
+            )
+        })?;
+        installed_dir(theme)?
+    };
+    THEME_FILES
+        .iter()
+        .map(|file| dir.join(file))
+        .find(|path| path.is_file())
+        .ok_or_else(|| {
+            friendly_message(&format!(
+                "The current theme has no theme.ron or theme.toml in {}",
+                dir.display()
+            ))
+        })
+}
+
+// Points the `current` symlink at `dir`, as `apply` does, in case it still
+// leads to the theme's own files rather than the generated copy.
+fn point_current(config_dir: &Path, dir: &Path) -> Result<(), errors::LeftError> {
+    let current = config_dir.join(THEMES_DIR).join(CURRENT_DIR);
+    if fs::read_link(&current).is_ok_and(|target| target == dir) {
+        return Ok(());
+    }
+    if current.symlink_metadata().is_ok() {
+        fs::remove_file(&current)?;
+    }
+    unix_fs::symlink(dir, current)?;
+    Ok(())
+}
+
+// The theme marked current in themes.toml.
+fn current_theme(config: &Config) -> Option<&Theme> {
+    config
+        .repos
+        .iter()
+        .flat_map(|repo| &repo.themes)
+        .find(|theme| theme.current == Some(true))
+}
+
+// The directory holding a theme's files, with `relative_directory` applied.
+fn installed_dir(theme: &Theme) -> Result<PathBuf, errors::LeftError> {
+    let mut dir = theme.directory.clone().ok_or_else(|| {
+        friendly_message(&format!(
+            "The current theme {} is not installed.",
+            &theme.name
+        ))
+    })?;
+    if let Some(rel_dir) = theme.relative_directory() {
+        dir.push(rel_dir);
+    }
+    Ok(dir)
+}
+
+// Converts the theme settings, including unknown fields, to a JSON value.
+fn theme_value(theme: &ThemeFile) -> Result<Value, errors::LeftError> {
+    let mut value = serde_json::to_value(theme)?;
+    if let Value::Object(map) = &mut value {
+        for (key, extra) in &theme.extra {
+            map.insert(key.clone(), serde_json::to_value(extra)?);
+        }
+    }
+    Ok(value)
+}
+
+// Looks up a dotted path such as `gutter.0.value`; numbers index into lists.
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(value, |value, key| match value {
+        Value::Object(map) => map.get(key),
+        Value::Array(list) => list.get(key.parse::<usize>().ok()?),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs;
+
+    const THEME_RON: &str = "#![enable(implicit_some)]
+(
+    border_width: 1,
+    margin: [1, 2, 3, 4],
+    gutter: [(side: Top, value: 24, id: None)],
+    focused_border_color: \"#FFB53A\",
+    bar_height: 22,
+)";
+
+    #[test]
+    fn test_lookup() {
+        let theme = ThemeFile::from_ron_str(THEME_RON).unwrap();
+        let value = theme_value(&theme).unwrap();
+        assert_eq!(lookup(&value, "border_width"), Some(&Value::from(1)));
+        assert_eq!(lookup(&value, "margin.2"), Some(&Value::from(3)));
+        assert_eq!(lookup(&value, "gutter.0.value"), Some(&Value::from(24)));
+        assert_eq!(lookup(&value, "gutter.0.side"), Some(&Value::from("Top")));
+        assert_eq!(lookup(&value, "bar_height"), Some(&Value::from(22)));
+        assert_eq!(lookup(&value, "background_color"), Some(&Value::Null));
+        assert!(lookup(&value, "gutter.1.value").is_none());
+        assert!(lookup(&value, "gutter.top").is_none());
+        assert!(lookup(&value, "nonsense").is_none());
+    }
+
+    #[test]
+    fn test_read_field_through_current_symlink() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let themes_dir = tmpdir.path().join(THEMES_DIR);
+        let nested = themes_dir.join("nested").join("theme");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(nested.join("theme.ron"), THEME_RON).unwrap();
+        fs::write(nested.join("theme.toml"), "border_width = 5").unwrap();
+        unix_fs::symlink(&nested, themes_dir.join(CURRENT_DIR)).unwrap();
+
+        let config = Config::new(Some(tmpdir.path().to_path_buf()));
+        assert_eq!(read_field(&config, "border_width").unwrap(), Value::from(1));
+        assert_eq!(
+            read_field(&config, "focused_border_color").unwrap(),
+            Value::from("#FFB53A")
+        );
+        assert!(read_field(&config, "background_color").is_err());
+        assert!(read_field(&config, "nonsense").is_err());
+    }
+
+    #[test]
+    fn test_read_field_from_config_with_relative_directory() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let theme_dir = tmpdir.path().join(THEMES_DIR).join("nested");
+        fs::create_dir_all(theme_dir.join("theme")).unwrap();
+        fs::write(
+            theme_dir.join("theme").join("theme.toml"),
+            "border_width = 5",
+        )
+        .unwrap();
+
+        let mut config = Config::new(Some(tmpdir.path().to_path_buf())).default();
+        let mut theme = Theme::new("nested", None, Some(theme_dir));
+        theme.set_relative_directory(Some(String::from("theme")));
+        theme.current(true);
+        config.repos[1].themes.push(theme);
+
+        assert_eq!(read_field(&config, "border_width").unwrap(), Value::from(5));
+    }
+
+    #[test]
+    fn test_set_field_in_ron_keeps_comments() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let path = tmpdir.path().join("theme.ron");
+        fs::write(
+            &path,
+            THEME_RON.replace("border_width: 1,", "border_width: 1, // thin"),
+        )
+        .unwrap();
+
+        set_field(&path, "border_width", "3").unwrap();
+        set_field(&path, "background_color", "#000000").unwrap();
+        set_field(&path, "gutter", r#"[{"side": "Bottom", "value": 20}]"#).unwrap();
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(contents.contains("border_width: 3, // thin"));
+        assert!(contents.contains("bar_height: 22"));
+
+        let value = theme_value(&ThemeFile::load(&path).unwrap()).unwrap();
+        assert_eq!(
+            lookup(&value, "background_color"),
+            Some(&Value::from("#000000"))
+        );
+        assert_eq!(
+            lookup(&value, "gutter.0.side"),
+            Some(&Value::from("Bottom"))
+        );
+    }
+
+    #[test]
+    fn test_set_field_in_toml() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let path = tmpdir.path().join("theme.toml");
+        fs::write(&path, "# mine\nborder_width = 1\nmargin = 5\n").unwrap();
+
+        set_field(&path, "margin", "[1, 2, 3, 4]").unwrap();
+        set_field(&path, "focused_border_color", "\"#FFB53A\"").unwrap();
+        let contents = fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            contents,
+            "# mine\nborder_width = 1\nmargin = [1, 2, 3, 4]\nfocused_border_color = \"#FFB53A\"\n"
+        );
+    }
+
+    #[test]
+    fn test_set_writes_to_overrides() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let theme_dir = tmpdir.path().join(THEMES_DIR).join("plain");
+        fs::create_dir_all(&theme_dir).unwrap();
+        fs::write(theme_dir.join("theme.ron"), THEME_RON).unwrap();
+        let overrides_file = overrides::file(tmpdir.path(), "plain");
+        fs::create_dir_all(overrides_file.parent().unwrap()).unwrap();
+        fs::write(&overrides_file, "(border_width: Some(2))").unwrap();
+
+        let mut config = Config::new(Some(tmpdir.path().to_path_buf())).default();
+        let mut theme = Theme::new("plain", None, Some(theme_dir.clone()));
+        theme.current(true);
+        config.repos[1].themes.push(theme);
+        // Applied before the overrides existed, so `current` leads to the theme itself.
+        let current = tmpdir.path().join(THEMES_DIR).join(CURRENT_DIR);
+        unix_fs::symlink(&theme_dir, &current).unwrap();
+
+        let set = Set {
+            field: String::from("margin"),
+            value: String::from("7"),
+            no_reset: true,
+        };
+        set.exec(&mut config).unwrap();
+        assert_eq!(
+            fs::read_to_string(theme_dir.join("theme.ron")).unwrap(),
+            THEME_RON
+        );
+        assert_eq!(
+            fs::read_to_string(&overrides_file).unwrap(),
+            "(border_width: Some(2),\n    margin: Some(7),\n)"
+        );
+        assert_eq!(read_field(&config, "margin").unwrap(), Value::from(7));
+        assert_eq!(read_field(&config, "border_width").unwrap(), Value::from(2));
+        assert_eq!(
+            fs::read_link(&current).unwrap(),
+            overrides::resolve(tmpdir.path(), "plain", &theme_dir).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_set_field_rejects_bad_values() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let path = tmpdir.path().join("theme.ron");
+        fs::write(&path, THEME_RON).unwrap();
+
+        assert!(set_field(&path, "border_width", "thick").is_err());
+        assert!(set_field(&path, "border_width", "1.5").is_err());
+        assert!(set_field(&path, "gutter", "[1]").is_err());
+        assert!(set_field(&path, "bar_height", "20").is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), THEME_RON);
+    }
+
+    #[test]
+    fn test_read_field_without_current_theme() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = Config::new(Some(tmpdir.path().to_path_buf())).default();
+        assert!(current_theme_file(&config).is_err());
     }
 }
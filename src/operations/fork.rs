@@ -0,0 +1,224 @@
+use crate::errors::{friendly_message, LeftError};
+use crate::models::config::LOCAL_REPO_NAME;
+use crate::models::{Config, Theme, THEMES_DIR};
+use crate::operations::new::New;
+use crate::utils;
+use crate::utils::git;
+use clap::Parser;
+use colored::Colorize;
+use git2::build::RepoBuilder;
+use git2::Repository;
+use log::trace;
+use std::fs;
+use std::io;
+use std::os::unix;
+use std::path::Path;
+
+/* This function copies an installed theme into a new LOCAL theme that can be
+   edited freely. The theme's git history is kept, with the original repository
+   as the `upstream` remote so its changes can be merged in later.
+*/
+
+const UPSTREAM_REMOTE: &str = "upstream";
+
+#[derive(Parser, Debug)]
+pub struct Fork {
+    /// Name of the installed theme to fork
+    pub name: String,
+
+    /// Name of the new theme
+    pub new_name: String,
+}
+
+impl Fork {
+    /// # Errors
+    ///
+    /// Will error if the theme is not installed.
+    /// Will error if the new name is not valid or already taken.
+    /// Will error if the theme cannot be cloned or copied, or config cannot be saved.
+    pub fn exec(&self, config: &mut Config) -> Result<(), LeftError> {
+        New::validate_name(&self.new_name)?;
+        let theme = Theme::find_installed(config, &self.name).ok_or_else(|| {
+            friendly_message(&format!(
+                "{} is not installed. Try `leftwm-theme install \"{}\"` first.",
+                &self.name, &self.name
+            ))
+        })?;
+        if Theme::find(config, &self.new_name).is_some() {
+            return Err(friendly_message(&format!(
+                "A theme named {} already exists.",
+                &self.new_name
+            )));
+        }
+        let target = config
+            .get_config_dir()?
+            .join(THEMES_DIR)
+            .join(&self.new_name);
+        if target.exists() {
+            return Err(friendly_message(&format!(
+                "{} already exists.",
+                target.display()
+            )));
+        }
+
+        let source = theme.directory.clone().unwrap_or_default();
+        fork_repository(&source, &target, theme.repository.as_deref())?;
+
+        let mut forked = Theme::new(
+            &self.new_name,
+            Some(match &theme.description {
+                Some(description) => format!("Fork of {}: {description}", &theme.name),
+                None => format!("Fork of {}", &theme.name),
+            }),
+            Some(target.clone()),
+        );
+        forked.repository = Some(target.display().to_string());
+        forked.leftwm_versions.clone_from(&theme.leftwm_versions);
+        forked.dependencies.clone_from(&theme.dependencies);
+        forked.support_url.clone_from(&theme.support_url);
+        forked.set_relative_directory(theme.relative_directory.clone());
+        Config::update_or_append(
+            config,
+            &forked,
+            (&String::from("localhost"), &String::from(LOCAL_REPO_NAME)),
+        );
+        Config::save(config)?;
+        println!(
+            "{} {} {} {}",
+            "Theme".green().bold(),
+            &self.new_name.red().bold(),
+            "forked successfully into".green().bold(),
+            target.display().to_string().red().bold()
+        );
+        println!(
+            "Merge upstream changes later with `git pull {UPSTREAM_REMOTE}` in that directory."
+        );
+        Ok(())
+    }
+}
+
+// Clones the installed theme (or copies it, if it is not a git repository) and
+// points the `upstream` remote at the theme's original repository.
+fn fork_repository(source: &Path, target: &Path, upstream: Option<&str>) -> Result<(), LeftError> {
+    let repo = if let Ok(source_repo) = Repository::open(source) {
+        trace!("Cloning {}", source.display());
+        let repo = RepoBuilder::new().clone(&source.display().to_string(), target)?;
+        // The clone's origin is the installed copy, which is not worth tracking.
+        repo.remote_delete("origin")?;
+        // Cloning only takes what was committed; edits made to the installed
+        // copy are carried over as uncommitted changes in the fork.
+        let changed = git::changed_paths(&source_repo, true)?;
+        if !changed.is_empty() {
+            println!(
+                "{}",
+                format!(
+                    "Carrying over {} uncommitted change(s) from {}.",
+                    changed.len(),
+                    source.display()
+                )
+                .yellow()
+            );
+        }
+        for path in changed {
+            copy_change(&source.join(&path), &target.join(&path))?;
+        }
+        repo
+    } else {
+        trace!("Copying {}", source.display());
+        utils::copy::tree(source, target)?.report();
+        Repository::init(target)?
+    };
+    let upstream = upstream.map_or_else(|| source.display().to_string(), String::from);
+    repo.remote(UPSTREAM_REMOTE, &upstream)?;
+    Ok(())
+}
+
+// Makes `to` match `from`, removing it if `from` was deleted.
+fn copy_change(from: &Path, to: &Path) -> io::Result<()> {
+    if to.symlink_metadata().is_ok() {
+        fs::remove_file(to)?;
+    }
+    let Ok(metadata) = from.symlink_metadata() else {
+        return Ok(());
+    };
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if metadata.file_type().is_symlink() {
+        unix::fs::symlink(fs::read_link(from)?, to)
+    } else {
+        fs::copy(from, to).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils::git::commit_files;
+
+    #[test]
+    fn test_fork_keeps_history_and_upstream() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let source = tmpdir.path().join(THEMES_DIR).join("original");
+        fs::create_dir_all(&source).unwrap();
+        Repository::init(&source).unwrap();
+        fs::write(source.join("theme.ron"), "()").unwrap();
+        commit_files(&source, &[&source.join("theme.ron")], "Initial commit").unwrap();
+        // Uncommitted edits are kept in the fork.
+        fs::write(source.join("theme.ron"), "(border_width: Some(2))").unwrap();
+        fs::write(source.join("up"), "#!/bin/sh").unwrap();
+
+        let mut config = Config::new(Some(tmpdir.path().to_path_buf())).default();
+        let mut theme = Theme::new("original", None, Some(source.clone()));
+        theme.repository = Some(String::from("https://example.com/original.git"));
+        config.repos[0].themes.push(theme);
+
+        let fork = Fork {
+            name: String::from("original"),
+            new_name: String::from("mine"),
+        };
+        fork.exec(&mut config).unwrap();
+
+        let target = tmpdir.path().join(THEMES_DIR).join("mine");
+        assert_eq!(
+            fs::read_to_string(target.join("theme.ron")).unwrap(),
+            "(border_width: Some(2))"
+        );
+        assert!(target.join("up").exists());
+        let repo = Repository::open(&target).unwrap();
+        assert!(repo.head().is_ok());
+        assert!(repo.find_remote("origin").is_err());
+        assert_eq!(
+            repo.find_remote(UPSTREAM_REMOTE).unwrap().url(),
+            Some("https://example.com/original.git")
+        );
+        let forked = Theme::find(&mut config, "mine").unwrap();
+        assert_eq!(forked.source.as_deref(), Some(LOCAL_REPO_NAME));
+        assert_eq!(forked.repository, Some(target.display().to_string()));
+
+        // Names are validated and cannot be reused.
+        assert!(fork.exec(&mut config).is_err());
+        let fork = Fork {
+            name: String::from("original"),
+            new_name: String::from("Not Valid"),
+        };
+        assert!(fork.exec(&mut config).is_err());
+    }
+
+    #[test]
+    fn test_fork_copies_plain_directories() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let source = tmpdir.path().join("plain");
+        let target = tmpdir.path().join("fork");
+        fs::create_dir_all(&source).unwrap();
+        fs::write(source.join("up"), "#!/bin/sh").unwrap();
+
+        fork_repository(&source, &target, None).unwrap();
+        assert!(target.join("up").exists());
+        let repo = Repository::open(&target).unwrap();
+        assert_eq!(
+            repo.find_remote(UPSTREAM_REMOTE).unwrap().url(),
+            Some(source.display().to_string().as_str())
+        );
+    }
+}
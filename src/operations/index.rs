@@ -0,0 +1,417 @@
+use super::lint::{self, Finding, Level};
+use super::publish_entry;
+use crate::errors::{friendly_message, LeftError};
+use crate::models::config::CURRENT_DEFINITIONS_VERSION;
+use crate::models::{Config, TempThemes, Theme};
+use crate::operations::new::New;
+use crate::utils;
+use clap::Parser;
+use colored::Colorize;
+use git2::{Oid, Repository};
+use semver::VersionReq;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use url::Url;
+
+/* These functions help maintainers of a theme index (a known.toml, like the
+   one of leftwm-community-themes) to keep it correct.
+*/
+
+#[derive(Parser, Debug)]
+pub struct Index {
+    #[clap(subcommand)]
+    pub action: IndexAction,
+}
+
+#[derive(Parser, Debug)]
+pub enum IndexAction {
+    /// Check a known.toml for problems
+    Check(Check),
+    /// Write a known.toml for a directory of theme repositories
+    Build(Build),
+}
+
+#[derive(Parser, Debug)]
+pub struct Check {
+    /// Path or URL of the known.toml
+    pub index: String,
+
+    /// Also clone every theme and run the `lint` checks on it
+    #[clap(long)]
+    pub deep: bool,
+}
+
+#[derive(Parser, Debug)]
+pub struct Build {
+    /// Directory holding one git repository per theme
+    pub dir: PathBuf,
+
+    /// Where to write the index; defaults to known.toml in the directory
+    #[clap(short = 'o', long)]
+    pub output: Option<PathBuf>,
+}
+
+impl Index {
+    /// # Errors
+    ///
+    /// Will error if the index cannot be read or parsed.
+    /// Will error if problems were found in the index.
+    /// Will error if an index cannot be built or written.
+    pub fn exec(&self, _config: &mut Config) -> Result<(), LeftError> {
+        match &self.action {
+            IndexAction::Check(args) => args.exec(),
+            IndexAction::Build(args) => args.exec(),
+        }
+    }
+}
+
+impl Check {
+    fn exec(&self) -> Result<(), LeftError> {
+        let index = TempThemes::parse(&utils::fetch::text(&self.index)?)?;
+        let mut errors = 0;
+        let mut warnings = 0;
+        let mut tally = |heading: &str, found: &[Finding]| {
+            let (e, w) = report(heading, found);
+            errors += e;
+            warnings += w;
+        };
+
+        tally(&self.index, &check_index(&index));
+        for (theme, found) in check_themes(&index.theme) {
+            let mut found = found;
+            if self.deep {
+                found.extend(deep_check(theme));
+            }
+            tally(&theme.name, &found);
+        }
+
+        if errors > 0 {
+            return Err(friendly_message(&format!(
+                "Found {errors} error(s) and {warnings} warning(s) in {} theme(s).",
+                index.theme.len()
+            )));
+        }
+        println!(
+            "{}",
+            format!(
+                "Checked {} theme(s): no errors, {warnings} warning(s).",
+                index.theme.len()
+            )
+            .bright_green()
+            .bold()
+        );
+        Ok(())
+    }
+}
+
+impl Build {
+    fn exec(&self) -> Result<(), LeftError> {
+        let dir = fs::canonicalize(&self.dir)?;
+        let themes = build(&dir)?;
+        if themes.is_empty() {
+            return Err(friendly_message(&format!(
+                "No theme repositories found in {}.",
+                dir.display()
+            )));
+        }
+        let mut errors = 0;
+        for (theme, found) in check_themes(&themes) {
+            errors += report(&theme.name, &found).0;
+        }
+        if errors > 0 {
+            return Err(friendly_message(&format!(
+                "Found {errors} error(s); fix them before building the index."
+            )));
+        }
+        let mut known = format!(
+            "# Generated by `leftwm-theme index build`\ndefinitions_version = {CURRENT_DEFINITIONS_VERSION}\n"
+        );
+        for theme in &themes {
+            known.push('\n');
+            known.push_str(&publish_entry::to_toml(theme)?);
+        }
+        let output = self
+            .output
+            .clone()
+            .unwrap_or_else(|| dir.join("known.toml"));
+        fs::write(&output, known)?;
+
+        println!(
+            "{}",
+            format!("Wrote {} theme(s) to {}.", themes.len(), output.display())
+                .bright_green()
+                .bold()
+        );
+        if let Ok(url) = Url::from_file_path(fs::canonicalize(&output)?) {
+            println!(
+                "To use it, add a [[repos]] entry with url = \"{url}\" to themes.toml and run `leftwm-theme update`."
+            );
+        }
+        Ok(())
+    }
+}
+
+// Builds an entry for every git repository directly inside `dir`, pointing
+// to it with a file:// URL.
+fn build(dir: &Path) -> Result<Vec<Theme>, LeftError> {
+    let mut themes = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if !path.is_dir() || Repository::open(&path).is_err() {
+            continue;
+        }
+        let Ok(url) = Url::from_directory_path(&path) else {
+            continue;
+        };
+        match publish_entry::entry(&path, Some(url.as_str())) {
+            Ok(theme) => themes.push(theme),
+            Err(err) => println!(
+                "{}",
+                format!("Skipping {}: {err}", path.display()).bright_yellow()
+            ),
+        }
+    }
+    themes.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(themes)
+}
+
+// Prints the findings under a heading; returns the number of errors and
+// warnings.
+fn report(heading: &str, found: &[Finding]) -> (usize, usize) {
+    if found.is_empty() {
+        return (0, 0);
+    }
+    println!("{}", heading.bold());
+    let mut errors = 0;
+    for finding in found {
+        println!("    {finding}");
+        if finding.level == Level::Error {
+            errors += 1;
+        }
+    }
+    (errors, found.len() - errors)
+}
+
+fn error(message: String) -> Finding {
+    Finding {
+        level: Level::Error,
+        file: None,
+        line: None,
+        message,
+    }
+}
+
+// Problems with the index as a whole.
+fn check_index(index: &TempThemes) -> Vec<Finding> {
+    let mut found = Vec::new();
+    if index.definitions_version > CURRENT_DEFINITIONS_VERSION {
+        found.push(error(format!(
+            "definitions_version {} is newer than the {CURRENT_DEFINITIONS_VERSION} this version of leftwm-theme understands",
+            index.definitions_version
+        )));
+    }
+    found
+}
+
+// Problems with each entry, which don't need the theme's repository.
+fn check_themes(themes: &[Theme]) -> Vec<(&Theme, Vec<Finding>)> {
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+    let mut result = Vec::new();
+    for (idx, theme) in themes.iter().enumerate() {
+        let mut found = Vec::new();
+        if let Some(first) = seen.get(theme.name.as_str()) {
+            found.push(error(format!(
+                "duplicate name; entry #{} has the same name",
+                first + 1
+            )));
+        } else {
+            seen.insert(&theme.name, idx);
+        }
+        if let Some(problem) = New::name_problem(&theme.name) {
+            found.push(error(format!("invalid name: {problem}")));
+        }
+        if let Some(versions) = &theme.leftwm_versions {
+            if let Err(err) = VersionReq::parse(versions) {
+                found.push(error(format!("leftwm_versions `{versions}`: {err}")));
+            }
+        }
+        match &theme.repository {
+            Some(repository) => {
+                if let Some(problem) = repository_problem(repository) {
+                    found.push(error(format!("repository `{repository}`: {problem}")));
+                }
+            }
+            None => found.push(error(String::from("no repository"))),
+        }
+        if let Some(commit) = &theme.commit {
+            if !is_full_commit(commit) {
+                found.push(error(format!(
+                    "commit `{commit}` is not `*` or a full commit SHA"
+                )));
+            }
+        }
+        if let Some(other) = &theme.replaced_by {
+            if !themes.iter().any(|theme| &theme.name == other) {
+                found.push(error(format!(
+                    "replaced_by `{other}` is not a theme in the index"
+                )));
+            }
+        }
+        result.push((theme, found));
+    }
+    result
+}
+
+// Why a repository URL cannot be cloned by users, if it can't.
+fn repository_problem(repository: &str) -> Option<String> {
+    match Url::parse(repository) {
+        Ok(url) => match url.scheme() {
+            "https" | "http" | "git" | "ssh" if url.host_str().is_none() => {
+                Some(String::from("the URL has no host"))
+            }
+            "https" | "http" | "git" | "ssh" | "file" => None,
+            scheme => Some(format!("unsupported scheme `{scheme}`")),
+        },
+        Err(err) => Some(err.to_string()),
+    }
+}
+
+fn is_full_commit(commit: &str) -> bool {
+    commit == "*"
+        || ((commit.len() == 40 || commit.len() == 64)
+            && commit.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+// Clones the theme at its commit and lints it.
+fn deep_check(theme: &Theme) -> Vec<Finding> {
+    let Some(repository) = &theme.repository else {
+        return Vec::new();
+    };
+    let cloned = tempfile::tempdir()
+        .map_err(LeftError::from)
+        .and_then(|tmpdir| {
+            let repo = Repository::clone(repository, tmpdir.path())?;
+            if let Some(commit) = theme.commit.as_ref().filter(|commit| *commit != "*") {
+                repo.set_head_detached(Oid::from_str(commit)?)?;
+                repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force()))?;
+            }
+            Ok(tmpdir)
+        });
+    match cloned {
+        Ok(tmpdir) => lint::lint(tmpdir.path(), Some(theme)),
+        Err(err) => vec![error(format!("could not clone the theme: {err}"))],
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_check_themes() {
+        let index: TempThemes = toml::from_str(
+            r#"
+[[theme]]
+name = "forest"
+repository = "https://github.com/me/forest"
+commit = "*"
+leftwm_versions = ">=0.4.0"
+
+[[theme]]
+name = "forest"
+repository = "github.com/me/forest"
+commit = "deadbeef"
+leftwm_versions = "0.4 or newer"
+
+[[theme]]
+name = "Orange Forest"
+repository = "ftp://example.com/orange"
+commit = "0123456789abcdef0123456789abcdef01234567"
+replaced_by = "forest-v2"
+"#,
+        )
+        .unwrap();
+
+        let messages: Vec<Vec<String>> = check_themes(&index.theme)
+            .into_iter()
+            .map(|(_, found)| found.into_iter().map(|f| f.message).collect())
+            .collect();
+        assert!(messages[0].is_empty());
+        assert_eq!(messages[1].len(), 4, "{:?}", messages[1]);
+        assert_eq!(messages[1][0], "duplicate name; entry #1 has the same name");
+        assert!(messages[1][1].starts_with("leftwm_versions `0.4 or newer`"));
+        assert!(messages[1][2].starts_with("repository `github.com/me/forest`"));
+        assert_eq!(
+            messages[1][3],
+            "commit `deadbeef` is not `*` or a full commit SHA"
+        );
+        assert_eq!(
+            messages[2],
+            vec![
+                "invalid name: a theme name can only contain lowercase alphanumeric characters and any of '@', '.', '_', '+', '-'",
+                "repository `ftp://example.com/orange`: unsupported scheme `ftp`",
+                "replaced_by `forest-v2` is not a theme in the index",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_index() {
+        let index = TempThemes {
+            definitions_version: CURRENT_DEFINITIONS_VERSION + 1,
+            theme: Vec::new(),
+        };
+        assert_eq!(check_index(&index).len(), 1);
+        assert!(check_index(&TempThemes::default()).is_empty());
+    }
+
+    #[test]
+    fn test_build() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let dir = fs::canonicalize(tmpdir.path()).unwrap();
+        for name in ["forest", "ocean"] {
+            let theme = dir.join(name);
+            fs::create_dir_all(&theme).unwrap();
+            Repository::init(&theme).unwrap();
+            fs::write(theme.join("up"), "#!/bin/sh\n").unwrap();
+            utils::git::commit_files(&theme, &[&theme.join("up")], "Initial commit").unwrap();
+        }
+        fs::create_dir_all(dir.join("not-a-repo")).unwrap();
+        Repository::init(dir.join("empty")).unwrap();
+
+        let themes = build(&dir).unwrap();
+        let names: Vec<&str> = themes.iter().map(|theme| theme.name.as_str()).collect();
+        assert_eq!(names, vec!["forest", "ocean"]);
+        let forest = &themes[0];
+        assert_eq!(
+            forest.repository.as_deref(),
+            Some(format!("file://{}/", dir.join("forest").display()).as_str())
+        );
+        assert_eq!(forest.commit.as_ref().map(String::len), Some(40));
+        assert!(check_themes(&themes)
+            .iter()
+            .all(|(_, found)| found.is_empty()));
+
+        // An index with invalid entries is not written.
+        let theme = dir.join("Bad Name");
+        fs::create_dir_all(&theme).unwrap();
+        Repository::init(&theme).unwrap();
+        fs::write(theme.join("up"), "#!/bin/sh\n").unwrap();
+        utils::git::commit_files(&theme, &[&theme.join("up")], "Initial commit").unwrap();
+        let build = Build {
+            dir: dir.clone(),
+            output: None,
+        };
+        assert!(build.exec().is_err());
+        assert!(!dir.join("known.toml").exists());
+    }
+
+    #[test]
+    fn test_is_full_commit() {
+        assert!(is_full_commit("*"));
+        assert!(is_full_commit("0123456789abcdef0123456789abcdef01234567"));
+        assert!(!is_full_commit("0123456"));
+        assert!(!is_full_commit("main"));
+    }
+}
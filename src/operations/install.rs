@@ -4,8 +4,8 @@ use crate::models::{Config, Theme};
 use crate::{errors, utils};
 use clap::Parser;
 use colored::Colorize;
-use git2::Repository;
-use log::{error, trace};
+use git2::build::RepoBuilder;
+use log::{error, trace, warn};
 use std::io;
 use std::io::Write;
 
@@ -35,9 +35,14 @@ impl Install {
 
         let mut found = Theme::find_all(config, &self.name)
             .ok_or_else(|| friendly_message("Could not find theme"))?;
+        // Themes removed from their repository can no longer be installed.
+        found.retain(|theme| theme.removed_upstream != Some(true));
 
         //ask the user to pick a matching theme
         let selected = choose_one(&mut found)?;
+        if let Some(notice) = selected.notice() {
+            warn!("{} is {notice}", &selected.name);
+        }
 
         //install the selected theme
         self.install_selected_theme(selected, config)?;
@@ -53,10 +58,14 @@ impl Install {
             .as_ref()
             .ok_or_else(|| friendly_message("Repository information missing for theme"))?;
         //build the path
-        let mut dir = utils::dir::theme()?;
+        let mut dir = utils::dir::theme(config)?;
         dir.push(&theme.name);
-        //clone the repo
-        Repository::clone(repo, dir.clone()).map_err(|err| {
+        //clone the repo, on the theme's branch if it names one
+        let mut builder = RepoBuilder::new();
+        if let Some(branch) = &theme.branch {
+            builder.branch(branch);
+        }
+        builder.clone(repo, &dir).map_err(|err| {
             let msg = format!(
                 "\n{} could not be installed because {:?} \n\n Theme not installed",
                 &theme.name,
@@ -0,0 +1,487 @@
+use crate::errors::{friendly_message, LeftError};
+use crate::models::theme_file::fields;
+use crate::models::{Config, Theme, ThemeFile};
+use crate::utils::palette::COLOR_FILES;
+use crate::utils::scripts;
+use clap::Parser;
+use colored::Colorize;
+use regex::Regex;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+/* This function checks that a theme is well-formed before it is published:
+   its scripts, its settings file and the dependencies it declares.
+   The exit status is non-zero if errors were found, for use in CI.
+*/
+
+#[derive(Parser, Debug)]
+pub struct Lint {
+    /// Directory of the theme to check; defaults to the current directory
+    pub path: Option<PathBuf>,
+
+    /// Treat warnings as errors
+    #[clap(short = 's', long)]
+    pub strict: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Level {
+    Error,
+    Warning,
+}
+
+/// A problem found in a theme.
+#[derive(Debug)]
+pub(crate) struct Finding {
+    pub(crate) level: Level,
+    /// The file, relative to the theme's repository
+    pub(crate) file: Option<PathBuf>,
+    pub(crate) line: Option<usize>,
+    pub(crate) message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.level {
+            Level::Error => write!(f, "{}: ", "error".bright_red().bold())?,
+            Level::Warning => write!(f, "{}: ", "warning".bright_yellow().bold())?,
+        }
+        if let Some(file) = &self.file {
+            write!(f, "{}", file.display())?;
+            if let Some(line) = self.line {
+                write!(f, ":{line}")?;
+            }
+            write!(f, ": ")?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+// Collects findings, with file paths relative to the repository root.
+struct Findings<'a> {
+    root: &'a Path,
+    found: Vec<Finding>,
+}
+
+impl Findings<'_> {
+    fn add(&mut self, level: Level, file: Option<&Path>, line: Option<usize>, message: String) {
+        let file = file.map(|file| file.strip_prefix(self.root).unwrap_or(file).to_path_buf());
+        self.found.push(Finding {
+            level,
+            file,
+            line,
+            message,
+        });
+    }
+
+    fn error(&mut self, file: &Path, line: Option<usize>, message: String) {
+        self.add(Level::Error, Some(file), line, message);
+    }
+
+    fn warning(&mut self, file: &Path, line: Option<usize>, message: String) {
+        self.add(Level::Warning, Some(file), line, message);
+    }
+}
+
+impl Lint {
+    /// # Errors
+    ///
+    /// Will error if the theme has errors, or warnings with `--strict`.
+    pub fn exec(&self, config: &mut Config) -> Result<(), LeftError> {
+        let root = self.path.clone().unwrap_or_else(|| PathBuf::from("."));
+        if !root.is_dir() {
+            return Err(friendly_message(&format!(
+                "{} is not a directory.",
+                root.display()
+            )));
+        }
+        let metadata = Theme::find_by_directory(config, &root);
+        let found = lint(&root, metadata.as_ref());
+        for finding in &found {
+            println!("{finding}");
+        }
+
+        let errors = found.iter().filter(|f| f.level == Level::Error).count();
+        let warnings = found.len() - errors;
+        if errors > 0 || (self.strict && warnings > 0) {
+            return Err(friendly_message(&format!(
+                "Found {errors} error(s) and {warnings} warning(s)."
+            )));
+        }
+        println!(
+            "{}",
+            format!("No errors found, {warnings} warning(s).")
+                .bright_green()
+                .bold()
+        );
+        Ok(())
+    }
+}
+
+/// Runs every check on the theme checked out at `root`.
+pub(crate) fn lint(root: &Path, metadata: Option<&Theme>) -> Vec<Finding> {
+    let mut findings = Findings {
+        root,
+        found: Vec::new(),
+    };
+    let mut dir = root.to_path_buf();
+    match metadata {
+        Some(theme) => {
+            if let Some(rel_dir) = theme.relative_directory() {
+                dir.push(&rel_dir);
+                if !dir.is_dir() {
+                    findings.add(
+                        Level::Error,
+                        None,
+                        None,
+                        format!("relative_directory `{rel_dir}` does not exist"),
+                    );
+                    return findings.found;
+                }
+            }
+        }
+        None => findings.add(
+            Level::Warning,
+            None,
+            None,
+            String::from(
+                "No metadata found (not an installed theme, and no index-entry.toml); relative_directory and dependencies are not checked",
+            ),
+        ),
+    }
+
+    check_up_down(&mut findings, &dir);
+    check_settings(&mut findings, &dir);
+    check_home_paths(&mut findings, &dir);
+    if let Some(theme) = metadata {
+        check_dependencies(&mut findings, &dir, theme);
+    }
+    findings.found
+}
+
+// `up` and `down` must exist, be executable and start with a shebang.
+fn check_up_down(findings: &mut Findings, dir: &Path) {
+    for name in ["up", "down"] {
+        let path = dir.join(name);
+        let Ok(metadata) = fs::metadata(&path) else {
+            findings.error(&path, None, format!("`{name}` is missing"));
+            continue;
+        };
+        if metadata.permissions().mode() & 0o111 == 0 {
+            findings.error(
+                &path,
+                None,
+                format!("`{name}` is not executable; run `chmod +x {name}`"),
+            );
+        }
+        let contents = fs::read_to_string(&path).unwrap_or_default();
+        if !contents.starts_with("#!") {
+            findings.error(
+                &path,
+                Some(1),
+                format!("`{name}` does not start with a shebang, e.g. `#!/usr/bin/env bash`"),
+            );
+        }
+    }
+}
+
+// The settings file must parse against the theme file schema, with valid colours.
+fn check_settings(findings: &mut Findings, dir: &Path) {
+    let mut any = false;
+    for name in ["theme.ron", "theme.toml"] {
+        let path = dir.join(name);
+        let Ok(contents) = fs::read_to_string(&path) else {
+            continue;
+        };
+        any = true;
+        let parsed = if name == "theme.ron" {
+            parse_ron(&contents)
+        } else {
+            parse_toml(&contents)
+        };
+        match parsed {
+            Ok((settings, keys)) => {
+                for key in keys.iter().filter(|key| !fields().contains(&key.as_str())) {
+                    findings.warning(
+                        &path,
+                        line_of(&contents, key),
+                        format!("unknown setting `{key}`"),
+                    );
+                }
+                check_colors(findings, &path, &contents, &settings);
+            }
+            Err((line, message)) => findings.error(&path, line, message),
+        }
+    }
+    if !any {
+        findings.error(
+            &dir.join("theme.ron"),
+            None,
+            String::from("neither theme.ron nor theme.toml exists"),
+        );
+    }
+}
+
+type Parsed = Result<(ThemeFile, BTreeSet<String>), (Option<usize>, String)>;
+
+fn parse_ron(contents: &str) -> Parsed {
+    let settings: ThemeFile =
+        ron::from_str(contents).map_err(|err| (Some(err.span.start.line), err.code.to_string()))?;
+    let keys = match ron::from_str::<ron::Value>(contents) {
+        Ok(ron::Value::Map(map)) => map
+            .keys()
+            .filter_map(|key| match key {
+                ron::Value::String(key) => Some(key.clone()),
+                _ => None,
+            })
+            .collect(),
+        _ => BTreeSet::new(),
+    };
+    Ok((settings, keys))
+}
+
+fn parse_toml(contents: &str) -> Parsed {
+    let error = |err: toml::de::Error| {
+        let line = err
+            .span()
+            .map(|span| contents[..span.start].matches('\n').count() + 1);
+        (line, err.message().to_string())
+    };
+    let settings: ThemeFile = toml::from_str(contents).map_err(error)?;
+    let table: toml::Table = toml::from_str(contents).map_err(error)?;
+    Ok((settings, table.keys().cloned().collect()))
+}
+
+fn check_colors(findings: &mut Findings, path: &Path, contents: &str, settings: &ThemeFile) {
+    let hex =
+        Regex::new(r"^#([0-9A-Fa-f]{3}|[0-9A-Fa-f]{6}|[0-9A-Fa-f]{8})$").expect("valid regex");
+    let colors = [
+        ("default_border_color", &settings.default_border_color),
+        ("floating_border_color", &settings.floating_border_color),
+        ("focused_border_color", &settings.focused_border_color),
+        ("background_color", &settings.background_color),
+    ];
+    for (field, value) in colors {
+        let Some(value) = value else {
+            continue;
+        };
+        let line = line_of(contents, field);
+        if !value.starts_with('#') {
+            findings.warning(path, line, format!("{field} `{value}` is not a hex colour"));
+        } else if !hex.is_match(value) {
+            findings.error(
+                path,
+                line,
+                format!("{field} `{value}` is not a valid hex colour"),
+            );
+        }
+    }
+}
+
+// Scripts and bar configurations must not depend on the author's home directory.
+fn check_home_paths(findings: &mut Findings, dir: &Path) {
+    let home = Regex::new(r"/home/[^/\s$\x22'`]+").expect("valid regex");
+    let mut files = scripts::find(dir);
+    files.extend(COLOR_FILES.iter().map(|file| dir.join(file)));
+    // An executable bar configuration is both; report it once.
+    files.sort();
+    files.dedup();
+    for path in files {
+        let Ok(contents) = fs::read_to_string(&path) else {
+            continue;
+        };
+        for (idx, line) in contents.lines().enumerate() {
+            if let Some(found) = home.find(line) {
+                findings.error(
+                    &path,
+                    Some(idx + 1),
+                    format!(
+                        "hard-coded home directory `{}`; use $HOME or paths relative to the theme",
+                        found.as_str()
+                    ),
+                );
+            }
+        }
+    }
+}
+
+// Programs run by the scripts should be declared dependencies, and the other
+// way round.
+fn check_dependencies(findings: &mut Findings, dir: &Path, theme: &Theme) {
+    let declared: BTreeSet<String> = theme
+        .dependencies
+        .iter()
+        .flatten()
+        .map(|dependency| dependency.program.clone())
+        .collect();
+    let mut all_scripts = String::new();
+    for path in scripts::find(dir) {
+        let Ok(contents) = fs::read_to_string(&path) else {
+            continue;
+        };
+        for program in scripts::called_programs(&contents) {
+            if !declared.contains(&program) {
+                findings.warning(
+                    &path,
+                    line_of(&contents, &program),
+                    format!("runs `{program}`, which is not a declared dependency"),
+                );
+            }
+        }
+        all_scripts.push_str(&contents);
+    }
+    for program in declared {
+        let used = Regex::new(&format!(r"(^|[^\w-]){}($|[^\w-])", regex::escape(&program)))
+            .is_ok_and(|re| re.is_match(&all_scripts));
+        if !used {
+            findings.add(
+                Level::Warning,
+                None,
+                None,
+                format!("dependency `{program}` is not used by any script"),
+            );
+        }
+    }
+}
+
+// The 1-based number of the first line containing `needle`.
+fn line_of(contents: &str, needle: &str) -> Option<usize> {
+    contents
+        .lines()
+        .position(|line| line.contains(needle))
+        .map(|idx| idx + 1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::models::DependencyL;
+
+    fn write_script(path: &Path, contents: &str) {
+        fs::write(path, contents).unwrap();
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    fn messages(found: &[Finding]) -> Vec<String> {
+        found
+            .iter()
+            .map(|f| {
+                let file = f
+                    .file
+                    .as_ref()
+                    .map(|file| format!("{}:{}: ", file.display(), f.line.unwrap_or(0)))
+                    .unwrap_or_default();
+                format!("{:?} {file}{}", f.level, f.message)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_lint_clean_theme() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let dir = tmpdir.path();
+        write_script(&dir.join("up"), "#!/bin/sh\npolybar &\n");
+        write_script(&dir.join("down"), "#!/bin/sh\npkill polybar\n");
+        fs::write(
+            dir.join("theme.ron"),
+            "#![enable(implicit_some)]\n(border_width: 1, focused_border_color: \"#FFB53A\")",
+        )
+        .unwrap();
+        let mut theme = Theme::new("clean", None, None);
+        theme.dependencies = Some(vec![DependencyL {
+            program: String::from("polybar"),
+            optional: None,
+            packages: None,
+        }]);
+
+        assert!(messages(&lint(dir, Some(&theme))).is_empty());
+    }
+
+    #[test]
+    fn test_lint_finds_problems() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let dir = tmpdir.path();
+        fs::write(dir.join("up"), "feh --bg-scale /home/me/wall.jpg\n").unwrap();
+        fs::write(
+            dir.join("theme.ron"),
+            "#![enable(implicit_some)]\n(\n    focused_border_color: \"#FFB53\",\n    default_border_color: \"red\",\n    bar_height: 20,\n)",
+        )
+        .unwrap();
+        let mut theme = Theme::new("broken", None, None);
+        theme.dependencies = Some(vec![DependencyL {
+            program: String::from("polybar"),
+            optional: None,
+            packages: None,
+        }]);
+
+        assert_eq!(
+            messages(&lint(dir, Some(&theme))),
+            vec![
+                "Error up:0: `up` is not executable; run `chmod +x up`",
+                "Error up:1: `up` does not start with a shebang, e.g. `#!/usr/bin/env bash`",
+                "Error down:0: `down` is missing",
+                "Warning theme.ron:5: unknown setting `bar_height`",
+                "Warning theme.ron:4: default_border_color `red` is not a hex colour",
+                "Error theme.ron:3: focused_border_color `#FFB53` is not a valid hex colour",
+                "Error up:1: hard-coded home directory `/home/me`; use $HOME or paths relative to the theme",
+                "Warning up:1: runs `feh`, which is not a declared dependency",
+                "Warning dependency `polybar` is not used by any script",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_home_paths_reported_once() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let dir = tmpdir.path();
+        fs::create_dir(dir.join("polybar")).unwrap();
+        write_script(
+            &dir.join("polybar").join("config"),
+            "#!/bin/sh
+wallpaper = /home/me/wall.jpg
+",
+        );
+        let mut findings = Findings {
+            root: dir,
+            found: Vec::new(),
+        };
+        check_home_paths(&mut findings, dir);
+        assert_eq!(
+            messages(&findings.found),
+            vec!["Error polybar/config:2: hard-coded home directory `/home/me`; use $HOME or paths relative to the theme"]
+        );
+    }
+
+    #[test]
+    fn test_lint_settings_parse_errors() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let dir = tmpdir.path();
+        write_script(&dir.join("up"), "#!/bin/sh\n");
+        write_script(&dir.join("down"), "#!/bin/sh\n");
+        fs::write(
+            dir.join("theme.toml"),
+            "border_width = 1\nmargin = \"wide\"\n",
+        )
+        .unwrap();
+
+        let found = lint(dir, None);
+        let messages = messages(&found);
+        assert_eq!(messages.len(), 2, "{messages:?}");
+        assert!(messages[0].starts_with("Warning No metadata found"));
+        assert!(messages[1].starts_with("Error theme.toml:2: "));
+    }
+
+    #[test]
+    fn test_lint_missing_relative_directory() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let mut theme = Theme::new("nested", None, None);
+        theme.set_relative_directory(Some(String::from("theme")));
+        assert_eq!(
+            messages(&lint(tmpdir.path(), Some(&theme))),
+            vec!["Error relative_directory `theme` does not exist"]
+        );
+    }
+}
@@ -28,16 +28,27 @@ impl List {
                     _ => "".white(),
                 };
                 if theme.directory.is_some() && !self.names {
+                    let notice = match theme.notice() {
+                        Some(notice) => format!(" ({notice})").bright_red(),
+                        None => "".white(),
+                    };
                     println!(
-                        "    {}{}/{}: {}",
+                        "    {}{}/{}: {}{}",
                         current,
                         repo.name.bright_magenta().bold(),
                         theme.name.bright_green().bold(),
                         theme
                             .description
                             .as_ref()
-                            .unwrap_or(&"A LeftWM theme".to_string())
+                            .unwrap_or(&"A LeftWM theme".to_string()),
+                        notice
                     );
+                    if theme.removed_upstream == Some(true) {
+                        println!(
+                            "        Run `leftwm-theme adopt {}` to keep it as a local theme.",
+                            &theme.name
+                        );
+                    }
                     installed += 1;
                 } else if theme.directory.is_some() && self.names {
                     println!("{}", theme.name);
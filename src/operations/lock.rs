@@ -0,0 +1,49 @@
+use crate::errors::{friendly_message, LeftError};
+use crate::models::lockfile::LOCK_FILE;
+use crate::models::{Config, LockFile};
+use clap::Parser;
+use colored::Colorize;
+use std::path::PathBuf;
+
+/* This function writes the installed themes, with their exact commits and the
+   current theme, to a lock file, so `leftwm-theme sync` can reproduce them on
+   another machine.
+*/
+
+#[derive(Parser, Debug)]
+pub struct Lock {
+    /// Where to write the lock file
+    #[clap(short = 'o', long, default_value = LOCK_FILE)]
+    pub output: PathBuf,
+}
+
+impl Lock {
+    /// # Errors
+    ///
+    /// Will error if no installed theme can be locked.
+    /// Will error if the lock file cannot be written.
+    pub fn exec(&self, config: &mut Config) -> Result<(), LeftError> {
+        let (lock, skipped) = LockFile::from_config(config);
+        for (name, reason) in &skipped {
+            println!(
+                "{}",
+                format!("Not locking {name}: {reason}").bright_yellow()
+            );
+        }
+        if lock.theme.is_empty() {
+            return Err(friendly_message("No installed themes could be locked."));
+        }
+        lock.save(&self.output)?;
+        println!(
+            "{}",
+            format!(
+                "Locked {} theme(s) in {}.",
+                lock.theme.len(),
+                self.output.display()
+            )
+            .bright_green()
+            .bold()
+        );
+        Ok(())
+    }
+}
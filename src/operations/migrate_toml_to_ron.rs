@@ -1,47 +1,77 @@
-use clap::Parser;
-use leftwm_core::models::Gutter;
-use log::trace;
-use serde::{Deserialize, Serialize};
-use std::fs;
+use clap::{Parser, ValueEnum};
+use colored::Colorize;
+use log::{error, trace};
 use std::fs::File;
 use std::io::Write;
 use std::path::{Path, PathBuf};
 
-use crate::errors::LeftError;
+use crate::errors::{friendly_message, LeftError};
+use crate::models::config::LOCAL_REPO_NAME;
+use crate::models::{Config, ThemeFile};
+use crate::utils;
+use crate::utils::read::yes_or_no;
 
 /* Thes function converts a `theme.toml` provided by the `path` arg
-   into a `theme.ron` at the same directory as the input file.
-    Required argument is the path to the file that should be converted.
+   into a `theme.ron` at the same directory as the input file, or back
+   with --to toml.
+    Required argument is the path to the file that should be converted,
+    unless --all is given to convert every installed theme.
 */
 
 #[derive(Debug, Parser)]
 pub struct Migrate {
-    pub path: PathBuf,
+    /// Path to the theme file to convert
+    #[clap(required_unless_present = "all")]
+    pub path: Option<PathBuf>,
+
+    /// Convert every installed theme which has no file in the target format yet
+    #[clap(short = 'a', long, conflicts_with = "path")]
+    pub all: bool,
+
+    /// Format to convert to
+    #[clap(short = 't', long, value_enum, default_value_t = Format::Ron)]
+    pub to: Format,
+
+    /// Print what would be written instead of writing it
+    #[clap(short = 'd', long)]
+    pub dry_run: bool,
+
+    /// Overwrite an existing file in the target format
+    #[clap(short = 'f', long)]
+    pub force: bool,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
-struct Theme {
-    pub border_width: Option<i32>,
-    pub margin: Option<CustomMargins>,
-    pub workspace_margin: Option<CustomMargins>,
-    pub default_width: Option<i32>,
-    pub default_height: Option<i32>,
-    pub always_float: Option<bool>,
-    pub gutter: Option<Vec<Gutter>>,
-    pub default_border_color: Option<String>,
-    pub floating_border_color: Option<String>,
-    pub focused_border_color: Option<String>,
-    pub background_color: Option<String>,
-    #[serde(rename = "on_new_window")]
-    pub on_new_window_cmd: Option<String>,
+/// Formats of a theme settings file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// `theme.ron`, read by current versions of `LeftWM`
+    Ron,
+    /// `theme.toml`, read by older versions of `LeftWM`
+    Toml,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
-#[serde(untagged)]
-enum CustomMargins {
-    Int(u32),
-    // format: [top, right, bottom, left] as per HTML
-    Vec(Vec<u32>),
+impl Format {
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Ron => "ron",
+            Format::Toml => "toml",
+        }
+    }
+
+    // The format converted from when converting to this one.
+    fn other(self) -> Format {
+        match self {
+            Format::Ron => Format::Toml,
+            Format::Toml => Format::Ron,
+        }
+    }
+
+    fn serialize(self, theme: &ThemeFile) -> Result<String, LeftError> {
+        match self {
+            Format::Ron => theme.to_ron_string(),
+            Format::Toml => theme.to_toml_string(),
+        }
+    }
 }
 
 impl Migrate {
@@ -49,39 +79,287 @@ impl Migrate {
     ///
     /// Returns an error if theme file cannot be loaded / saved
     /// Returns an error if theme not found.
-    pub fn exec(&self) -> Result<(), LeftError> {
-        trace!("Migrating theme named {}", &self.path.display());
-        match migrate(&self.path) {
-            Ok(()) => Ok(()),
-            Err(_) => Err(LeftError::from("Failed to migrate theme.")),
+    /// Returns an error if the target file exists and --force was not given.
+    pub fn exec(&self, config: &mut Config) -> Result<(), LeftError> {
+        if self.all {
+            return self.migrate_all(config);
+        }
+        let Some(path) = &self.path else {
+            return Err(friendly_message("Nothing to migrate."));
+        };
+        trace!("Migrating theme named {}", &path.display());
+        match self.migrate(path) {
+            Ok(_) => Ok(()),
+            Err(err) => {
+                error!("{err}");
+                Err(LeftError::from("Failed to migrate theme."))
+            }
         }
     }
-}
 
-fn migrate(path: &PathBuf) -> Result<(), LeftError> {
-    let Ok(theme) = load_theme_file(path) else {
-        return Err(LeftError::from("Theme not found"));
-    };
-    let mut ron_path = path.clone();
-    ron_path.set_extension("ron");
-    match write_to_file(&ron_path, &theme) {
-        Ok(()) => Ok(()),
-        Err(_) => Err(LeftError::from("Failed to write theme file.")),
+    // Migrates every installed theme that has a theme file in the source
+    // format but not in the target format.
+    fn migrate_all(&self, config: &mut Config) -> Result<(), LeftError> {
+        let source_name = format!("theme.{}", self.to.other().extension());
+        let target_name = format!("theme.{}", self.to.extension());
+        let mut migrated = 0;
+        for theme in config.themes(false) {
+            let Some(mut dir) = theme.directory.clone() else {
+                continue;
+            };
+            if let Some(rel_dir) = theme.relative_directory() {
+                dir.push(rel_dir);
+            }
+            let source_path = dir.join(&source_name);
+            let target_path = dir.join(&target_name);
+            if !source_path.exists() || (target_path.exists() && !self.force) {
+                trace!("Nothing to migrate for {}", &theme.name);
+                continue;
+            }
+
+            println!(
+                "{}{}",
+                "Migrating ".bright_blue().bold(),
+                &theme.name.bright_green().bold()
+            );
+            match self.migrate(&source_path) {
+                Ok(true) => {
+                    migrated += 1;
+                    if theme.source.as_deref() == Some(LOCAL_REPO_NAME)
+                        && yes_or_no(&format!(
+                            "Commit the new {target_name} to the repository of {}?",
+                            &theme.name
+                        ))
+                    {
+                        utils::git::commit_files(
+                            &dir,
+                            &[&target_path],
+                            &format!("Migrate {source_name} to {target_name}"),
+                        )?;
+                    }
+                }
+                Ok(false) => {}
+                Err(err) => error!("Could not migrate {}: {err}", &theme.name),
+            }
+        }
+        if !self.dry_run {
+            println!(
+                "{}",
+                format!("Migrated {migrated} theme(s).")
+                    .bright_blue()
+                    .bold()
+            );
+        }
+        Ok(())
+    }
+
+    // Converts a single theme file, returning whether a file was written.
+    fn migrate(&self, path: &Path) -> Result<bool, LeftError> {
+        let theme = load_theme_file(path)?;
+        let target_path = path.with_extension(self.to.extension());
+        if target_path == path {
+            return Err(friendly_message(&format!(
+                "{} is already a {} file.",
+                path.display(),
+                self.to.extension()
+            )));
+        }
+        let contents = self.to.serialize(&theme)?;
+        if self.dry_run {
+            println!("{}", format!("{}:", target_path.display()).bold());
+            match std::fs::read_to_string(&target_path) {
+                Ok(existing) => utils::diff::print(&existing, &contents),
+                Err(_) => println!("{contents}"),
+            }
+            return Ok(false);
+        }
+        if target_path.exists() && !self.force {
+            return Err(friendly_message(&format!(
+                "{} already exists. Use --force to overwrite it.",
+                target_path.display()
+            )));
+        }
+        write_to_file(&target_path, &contents)?;
+        Ok(true)
     }
 }
 
-fn write_to_file(ron_file: &PathBuf, theme: &Theme) -> Result<(), LeftError> {
-    let ron_pretty_conf = ron::ser::PrettyConfig::new()
-        .depth_limit(2)
-        .extensions(ron::extensions::Extensions::IMPLICIT_SOME);
-    let ron_theme = ron::ser::to_string_pretty(&theme, ron_pretty_conf)?;
-    let mut file = File::create(ron_file)?;
-    file.write_all(ron_theme.as_bytes())?;
+fn write_to_file(path: &Path, contents: &str) -> Result<(), LeftError> {
+    let mut file = File::create(path)?;
+    file.write_all(contents.as_bytes())?;
     Ok(())
 }
 
-fn load_theme_file(path: impl AsRef<Path>) -> Result<Theme, LeftError> {
-    let contents = fs::read_to_string(&path)?;
-    let from_file: Theme = toml::from_str(&contents)?;
-    Ok(from_file)
+fn load_theme_file(path: impl AsRef<Path>) -> Result<ThemeFile, LeftError> {
+    ThemeFile::load(path.as_ref())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::models::Theme;
+
+    // Golden files adapted from themes in the community repository.
+    const GOLDEN: &[(&str, &str, &str)] = &[
+        (
+            "orange-forest",
+            include_str!("../../testdata/migrate/orange-forest/theme.toml"),
+            include_str!("../../testdata/migrate/orange-forest/theme.ron"),
+        ),
+        (
+            "coffee",
+            include_str!("../../testdata/migrate/coffee/theme.toml"),
+            include_str!("../../testdata/migrate/coffee/theme.ron"),
+        ),
+        (
+            "blue-ocean",
+            include_str!("../../testdata/migrate/blue-ocean/theme.toml"),
+            include_str!("../../testdata/migrate/blue-ocean/theme.ron"),
+        ),
+    ];
+
+    #[test]
+    fn test_migrate_golden_files() {
+        for (name, toml_theme, ron_theme) in GOLDEN {
+            let theme = ThemeFile::from_toml_str(toml_theme).unwrap();
+            assert_eq!(
+                theme.to_ron_string().unwrap(),
+                ron_theme.trim_end(),
+                "{name}"
+            );
+            // Every field survives being read back.
+            let reread = ThemeFile::from_ron_str(ron_theme).unwrap();
+            assert_eq!(reread.extra.len(), theme.extra.len(), "{name}");
+            assert_eq!(
+                reread.to_ron_string().unwrap(),
+                ron_theme.trim_end(),
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_migrate_golden_files_to_toml_and_back() {
+        for (name, toml_theme, ron_theme) in GOLDEN {
+            let theme = ThemeFile::from_ron_str(ron_theme).unwrap();
+            let toml_again = Format::Toml.serialize(&theme).unwrap();
+            let theme_again = ThemeFile::from_toml_str(&toml_again).unwrap();
+            assert_eq!(
+                theme_again,
+                ThemeFile::from_toml_str(toml_theme).unwrap(),
+                "{name}"
+            );
+            assert_eq!(
+                Format::Ron.serialize(&theme_again).unwrap(),
+                ron_theme.trim_end(),
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_migrate_writes_toml_next_to_ron() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let ron_path = tmpdir.path().join("theme.ron");
+        std::fs::write(&ron_path, GOLDEN[1].2).unwrap();
+
+        let mut args = migrate_args(&ron_path);
+        args.to = Format::Toml;
+        assert!(args.migrate(&ron_path).unwrap());
+        let toml_theme = std::fs::read_to_string(tmpdir.path().join("theme.toml")).unwrap();
+        assert_eq!(
+            ThemeFile::from_toml_str(&toml_theme).unwrap(),
+            ThemeFile::from_toml_str(GOLDEN[1].1).unwrap()
+        );
+        // Converting a file to its own format is refused.
+        assert!(args.migrate(&tmpdir.path().join("theme.toml")).is_err());
+    }
+
+    #[test]
+    fn test_migrate_writes_ron_next_to_toml() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let toml_path = tmpdir.path().join("theme.toml");
+        std::fs::write(&toml_path, GOLDEN[0].1).unwrap();
+
+        assert!(migrate(&toml_path).is_ok());
+        let ron_theme = std::fs::read_to_string(tmpdir.path().join("theme.ron")).unwrap();
+        assert_eq!(ron_theme, GOLDEN[0].2.trim_end());
+    }
+
+    #[test]
+    fn test_migrate_refuses_to_overwrite() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let toml_path = tmpdir.path().join("theme.toml");
+        let ron_path = tmpdir.path().join("theme.ron");
+        std::fs::write(&toml_path, GOLDEN[0].1).unwrap();
+        std::fs::write(&ron_path, "()").unwrap();
+
+        let mut args = migrate_args(&toml_path);
+        assert!(args.migrate(&toml_path).is_err());
+        args.dry_run = true;
+        assert!(!args.migrate(&toml_path).unwrap());
+        assert_eq!(std::fs::read_to_string(&ron_path).unwrap(), "()");
+        args.dry_run = false;
+        args.force = true;
+        assert!(args.migrate(&toml_path).unwrap());
+        assert_eq!(
+            std::fs::read_to_string(&ron_path).unwrap(),
+            GOLDEN[0].2.trim_end()
+        );
+    }
+
+    #[test]
+    fn test_migrate_all() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let themes_dir = tmpdir.path().join(crate::models::THEMES_DIR);
+        let plain = themes_dir.join("plain");
+        let nested = themes_dir.join("nested");
+        let done = themes_dir.join("done");
+        std::fs::create_dir_all(&plain).unwrap();
+        std::fs::create_dir_all(nested.join("theme")).unwrap();
+        std::fs::create_dir_all(&done).unwrap();
+        std::fs::write(plain.join("theme.toml"), GOLDEN[0].1).unwrap();
+        std::fs::write(nested.join("theme").join("theme.toml"), GOLDEN[1].1).unwrap();
+        std::fs::write(done.join("theme.toml"), GOLDEN[0].1).unwrap();
+        std::fs::write(done.join("theme.ron"), "()").unwrap();
+
+        let mut config = Config::new(Some(tmpdir.path().to_path_buf())).default();
+        let mut nested_theme = Theme::new("nested", None, Some(nested.clone()));
+        nested_theme.set_relative_directory(Some(String::from("theme")));
+        config.repos[0].themes = vec![
+            Theme::new("plain", None, Some(plain.clone())),
+            nested_theme,
+            Theme::new("done", None, Some(done.clone())),
+            Theme::new("uninstalled", None, None),
+        ];
+
+        let args = Migrate {
+            path: None,
+            all: true,
+            to: Format::Ron,
+            dry_run: false,
+            force: false,
+        };
+        assert!(args.exec(&mut config).is_ok());
+        assert!(plain.join("theme.ron").exists());
+        assert!(nested.join("theme").join("theme.ron").exists());
+        assert_eq!(
+            std::fs::read_to_string(done.join("theme.ron")).unwrap(),
+            "()"
+        );
+    }
+
+    fn migrate_args(path: &Path) -> Migrate {
+        Migrate {
+            path: Some(path.to_path_buf()),
+            all: false,
+            to: Format::Ron,
+            dry_run: false,
+            force: false,
+        }
+    }
+
+    fn migrate(path: &Path) -> Result<bool, LeftError> {
+        migrate_args(path).migrate(path)
+    }
 }
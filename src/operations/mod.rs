@@ -1,27 +1,47 @@
+mod adopt;
 mod apply;
 //mod autofind;
 mod current;
+mod fork;
+mod index;
 mod install;
+mod lint;
 mod list;
+mod lock;
 mod migrate_toml_to_ron;
 mod new;
+mod palette;
+mod publish_entry;
+mod recolor;
+mod repair;
 mod search;
 mod status;
 mod support;
+mod sync;
 mod uninstall;
 mod update;
 mod upgrade;
 
+pub use adopt::Adopt;
 pub use apply::Apply;
 //pub use autofind::AutoFind;
 pub use current::Current;
+pub use fork::Fork;
+pub use index::Index;
 pub use install::Install;
+pub use lint::Lint;
 pub use list::List;
+pub use lock::Lock;
 pub use migrate_toml_to_ron::Migrate;
 pub use new::New;
+pub use palette::Palette;
+pub use publish_entry::PublishEntry;
+pub use recolor::Recolor;
+pub use repair::Repair;
 pub use search::Search;
 pub use status::Status;
 pub use support::Support;
+pub use sync::SyncThemes;
 pub use uninstall::Uninstall;
 pub use update::Update;
 pub use upgrade::Upgrade;
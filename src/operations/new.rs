@@ -1,19 +1,34 @@
 use crate::errors;
-use crate::models::{Config, Theme};
+use crate::models::template::{check_value, render_dir, Variable, TEMPLATE_CONFIG_FILE};
+use crate::models::{Config, Template, Theme};
+use crate::utils;
 use crate::utils::read::one;
 use clap::Parser;
 use colored::Colorize;
 use git2::Repository;
 use log::{error, trace};
 use regex::Regex;
+use std::collections::BTreeMap;
 use std::io;
-use std::io::Write;
-use std::path::Path;
-use xdg::BaseDirectories;
+use std::io::{IsTerminal, Write};
 
 #[derive(Parser, Debug)]
 pub struct New {
-    pub name: String,
+    /// Name of the new theme
+    #[clap(required_unless_present = "list_templates")]
+    pub name: Option<String>,
+
+    /// Template to prefill the theme from: a template or theme name, a directory or a git URL
+    #[clap(short = 't', long)]
+    pub template: Option<String>,
+
+    /// Set a template variable instead of being asked for it, e.g. `author=me`
+    #[clap(short = 's', long = "set", value_parser = parse_variable)]
+    pub set: Vec<(String, String)>,
+
+    /// List the templates a theme can be prefilled from
+    #[clap(short = 'l', long, conflicts_with_all = ["name", "template"])]
+    pub list_templates: bool,
 }
 
 // TODO: Allow themes with the same name in different namespaces
@@ -22,143 +37,293 @@ impl New {
     ///
     /// Will send an error if theme has a `/`.
     /// Will error if a theme with same name already exists.
+    /// Will error if the template is not found or cannot be fetched.
     /// Will error if config cannot be loaded or saved properly.
     pub fn exec(&self, config: &mut Config) -> Result<(), errors::LeftError> {
-        New::validate_name(&self.name)?;
-
-        if let Some(_theme) = Theme::find(config, &self.name) {
-            error!(
-                "\n{} could not be created because a theme with that name already exists",
-                &self.name,
-            );
-            Err(errors::LeftError::from("Theme not installed"))
-        } else {
-            //Create the new git in the leftwm directory
-            let mut dir = BaseDirectories::with_prefix("leftwm").create_config_directory("")?;
-            dir.push("themes");
-            dir.push(&self.name);
-            match Repository::init(&dir) {
-                Ok(_repo) => {
-                    Config::update_or_append(
-                        config,
-                        &Theme::new(&self.name, None, Some(dir.clone())),
-                        (&String::from("localhost"), &String::from("LOCAL")),
-                    );
-                    Config::save(config)?;
-                    println!(
-                        "{} {} {} {}",
-                        "Theme".green().bold(),
-                        &self.name.red().bold(),
-                        "created successfully in".green().bold(),
-                        dir.to_str().unwrap_or("Unknown directory").red().bold()
-                    );
-                    println!(
-                        "{}Which theme would you like to prefill?",
-                        "::".bright_yellow().bold()
-                    );
+        if self.list_templates {
+            return list_templates(config);
+        }
+        let Some(name) = &self.name else {
+            return Err(errors::friendly_message("No theme name given."));
+        };
+        New::validate_name(name)?;
+
+        if let Some(_theme) = Theme::find(config, name) {
+            error!("\n{name} could not be created because a theme with that name already exists");
+            return Err(errors::LeftError::from("Theme not installed"));
+        }
+        let template = match &self.template {
+            Some(wanted) => Some(Template::find(config, wanted)?),
+            None => choose_template(Template::list(config)?),
+        };
+        // Fetch the template before creating anything, so a failed download
+        // does not leave a half-created theme behind.
+        let fetched = match template {
+            Some(template) => {
+                println!(
+                    "{}{}",
+                    "Prefilling from ".bright_blue().bold(),
+                    &template.name.bright_green().bold()
+                );
+                let fetched = template.fetch()?;
+                let variables = fetched.variables()?;
+                Some((fetched, variables))
+            }
+            None => None,
+        };
+
+        //Create the new git in the leftwm directory
+        let dir = utils::dir::theme(config)?.join(name);
+        match Repository::init(&dir) {
+            Ok(_repo) => {
+                Config::update_or_append(
+                    config,
+                    &Theme::new(name, None, Some(dir.clone())),
+                    (&String::from("localhost"), &String::from("LOCAL")),
+                );
+                Config::save(config)?;
+                println!(
+                    "{} {} {} {}",
+                    "Theme".green().bold(),
+                    name.red().bold(),
+                    "created successfully in".green().bold(),
+                    dir.to_str().unwrap_or("Unknown directory").red().bold()
+                );
+                let Some((fetched, variables)) = fetched else {
+                    trace!("Doing nothing");
+                    return Ok(());
+                };
+                utils::copy::tree(&fetched.path, &dir)?.report();
+                let config_file = dir.join(TEMPLATE_CONFIG_FILE);
+                if config_file.exists() {
+                    std::fs::remove_file(config_file)?;
+                }
+                let values = self.values(
+                    name,
+                    &variables,
+                    self.interactive(io::stdin().is_terminal()),
+                );
+                render_dir(&dir, &values)
+            }
+            Err(e) => {
+                error!("\n{} could not be created because {:?}", name, e.message());
+                Err(errors::LeftError::from("Theme not created"))
+            }
+        }
+    }
+
+    // Questions are only asked on a terminal, and not when the template was
+    // given with --template, which makes `new` usable from scripts.
+    fn interactive(&self, terminal: bool) -> bool {
+        self.template.is_none() && terminal
+    }
+
+    // Values of the template variables: the theme name, then each variable
+    // from --set, else asked for when interactive, else its default.
+    fn values(
+        &self,
+        name: &str,
+        variables: &[Variable],
+        interactive: bool,
+    ) -> BTreeMap<String, String> {
+        let mut values: BTreeMap<String, String> = self.set.iter().cloned().collect();
+        values.insert(String::from("name"), name.to_string());
+        for variable in variables {
+            if values.contains_key(&variable.name) {
+                continue;
+            }
+            let value = if interactive {
+                loop {
                     print!(
-                        "  [0] basic_lemonbar\n  [1] basic_polybar\n  [2] basic_xmobar\n  [3] None\n"
+                        "{}{} [{}]: ",
+                        "::".bright_yellow().bold(),
+                        variable.prompt.as_ref().unwrap_or(&variable.name),
+                        &variable.default
                     );
-                    let state = loop {
-                        print!("{}", "0-3 =>".bright_yellow().bold());
-                        io::stdout().flush().unwrap_or_default();
-                        let state = one().trim().to_uppercase();
-
-                        if state == *"0" || state == *"1" || state == *"2" || state == *"3" {
-                            break state;
-                        }
-
-                        println!("Please write a number 0-3.");
-                    };
-                    match state.as_str() {
-                        "0" => copy_files("/usr/share/leftwm/themes/basic_lemonbar/", &dir),
-                        "1" => copy_files("/usr/share/leftwm/themes/basic_polybar/", &dir),
-                        "2" => copy_files("/usr/share/leftwm/themes/basic_xmobar/", &dir),
-                        _ => {
-                            trace!("Doing nothing");
-                            Ok(())
-                        }
+                    io::stdout().flush().unwrap_or_default();
+                    let answer = one().trim().to_string();
+                    if answer.is_empty() {
+                        break variable.default.clone();
+                    }
+                    match check_value(&answer) {
+                        Ok(()) => break answer,
+                        Err(problem) => println!("{}", problem.bright_red()),
                     }
                 }
-                Err(e) => {
-                    error!(
-                        "\n{} could not be created because {:?}",
-                        &self.name,
-                        e.message()
-                    );
-                    Err(errors::LeftError::from("Theme not created"))
-                }
-            }
+            } else {
+                variable.default.clone()
+            };
+            values.insert(variable.name.clone(), value);
         }
+        values
     }
 
     // Validates a given name for the theme name.
-    fn validate_name(name: &str) -> Result<bool, errors::LeftError> {
-        let mut valid: bool = true;
+    pub(crate) fn validate_name(name: &str) -> Result<bool, errors::LeftError> {
+        if let Some(problem) = New::name_problem(name) {
+            error!("\n{} could not be created because {}", &name, problem);
+            return Err(errors::LeftError::from("Theme name not valid."));
+        }
+        Ok(true)
+    }
 
+    /// Returns why a theme name is not valid, if it isn't.
+    pub(crate) fn name_problem(name: &str) -> Option<&'static str> {
         // Should not contain '/'.
         if name.contains('/') {
-            error!(
-                "\n{} could not be created because a theme name should not contain '/'",
-                &name,
-            );
-            valid = false;
+            return Some("a theme name should not contain '/'");
         }
 
         // Check for allowed characters.
         let re = Regex::new(r"^[a-z0-9_+-@.]*$").unwrap();
-        if valid && !re.is_match(name) {
-            error!(
-                "\n{} could not be created because a theme name can only contain lowercase alphanumeric characters and any of '@', '.', '_', '+', '-'",
-                &name,
-            );
-            valid = false;
+        if !re.is_match(name) {
+            return Some("a theme name can only contain lowercase alphanumeric characters and any of '@', '.', '_', '+', '-'");
         }
 
         // Should not have hyphens or dots at the beginning.
         let starts_with_re = Regex::new(r"^[-.]").unwrap();
-        if valid && starts_with_re.is_match(name) {
-            error!(
-                "\n{} could not be created because a theme name should not start with hyphens or dots",
-                &name,
-            );
-            valid = false;
-        }
-
-        if !valid {
-            return Err(errors::LeftError::from("Theme name not valid."));
+        if starts_with_re.is_match(name) {
+            return Some("a theme name should not start with hyphens or dots");
         }
 
-        Ok(true)
+        None
     }
 }
 
-fn copy_files(dir: &str, left_path: &Path) -> Result<(), errors::LeftError> {
-    trace!("{:?}", &dir);
-    let directory = Path::new(dir);
-    trace!("{}", &directory.display());
-    if directory.is_dir() {
-        trace!("Directory Exists");
-        for entry in std::fs::read_dir(directory)? {
-            trace!("{:?}", &entry);
-            let entry = entry?;
-            let path = entry.path();
-            trace!(
-                "{:?}",
-                std::fs::copy(path, left_path.join(entry.file_name()))
-            );
+// Parses `key=value` given to --set.
+fn parse_variable(arg: &str) -> Result<(String, String), String> {
+    match arg.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => {
+            check_value(value)?;
+            Ok((key.trim().to_string(), value.to_string()))
         }
-    } else {
-        error!("Basic themes directory /usr/share/leftwm/ not found. Was it installed by LeftWM?");
-        return Err(errors::LeftError::from("Theme not prefilled"));
+        _ => Err(format!("expected key=value, got {arg:?}")),
     }
+}
+
+// Prints the templates a theme can be prefilled from.
+fn list_templates(config: &mut Config) -> Result<(), errors::LeftError> {
+    let templates = Template::list(config)?;
+    if templates.is_empty() {
+        println!("{}", "No templates found.".bright_blue().bold());
+    }
+    for template in templates {
+        println!(
+            "    {} ({}): {}",
+            template.name.bright_green().bold(),
+            template.source,
+            template.location
+        );
+    }
+    println!("A git URL or a directory can also be given with --template.");
     Ok(())
 }
 
+// Asks which template to prefill a new theme from, if there are any.
+fn choose_template(templates: Vec<Template>) -> Option<Template> {
+    if templates.is_empty() {
+        return None;
+    }
+    println!(
+        "{}Which theme would you like to prefill?",
+        "::".bright_yellow().bold()
+    );
+    for (i, template) in templates.iter().enumerate() {
+        println!("  [{i}] {} ({})", template.name, template.source);
+    }
+    println!("  [{}] None", templates.len());
+    let choice = loop {
+        print!(
+            "{}",
+            format!("0-{} =>", templates.len()).bright_yellow().bold()
+        );
+        io::stdout().flush().unwrap_or_default();
+        match one().trim().parse::<usize>() {
+            Ok(choice) if choice <= templates.len() => break choice,
+            _ => println!("Please write a number 0-{}.", templates.len()),
+        }
+    };
+    templates.into_iter().nth(choice)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
+    #[test]
+    fn test_parse_variable() {
+        assert_eq!(
+            parse_variable("author=me=you"),
+            Ok((String::from("author"), String::from("me=you")))
+        );
+        assert_eq!(
+            parse_variable("font="),
+            Ok((String::from("font"), String::new()))
+        );
+        assert!(parse_variable("author").is_err());
+        assert!(parse_variable("description=a \"b\"").is_err());
+        assert!(parse_variable("=me").is_err());
+    }
+
+    #[test]
+    fn test_values() {
+        let new = New {
+            name: Some(String::from("mine")),
+            template: None,
+            set: vec![
+                (String::from("author"), String::from("me")),
+                (String::from("extra"), String::from("1")),
+            ],
+            list_templates: false,
+        };
+        let variable = |name: &str, default: &str| Variable {
+            name: name.to_string(),
+            prompt: None,
+            default: default.to_string(),
+        };
+        let values = new.values(
+            "mine",
+            &[variable("author", "someone"), variable("font", "monospace")],
+            false,
+        );
+        assert_eq!(values["name"], "mine");
+        assert_eq!(values["author"], "me");
+        assert_eq!(values["font"], "monospace");
+        assert_eq!(values["extra"], "1");
+    }
+
+    #[test]
+    fn test_template_flag_is_not_interactive() {
+        let mut new = New {
+            name: Some(String::from("mine")),
+            template: None,
+            set: Vec::new(),
+            list_templates: false,
+        };
+        assert!(new.interactive(true));
+        assert!(!new.interactive(false));
+        new.template = Some(String::from("default"));
+        assert!(!new.interactive(true));
+    }
+
+    #[test]
+    fn test_broken_template_creates_nothing() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let template = tmpdir.path().join("broken");
+        std::fs::create_dir_all(&template).unwrap();
+        std::fs::write(template.join(TEMPLATE_CONFIG_FILE), "variables = 1").unwrap();
+        let mut config = Config::new(Some(tmpdir.path().to_path_buf())).default();
+        let new = New {
+            name: Some(String::from("mine")),
+            template: Some(template.display().to_string()),
+            set: Vec::new(),
+            list_templates: false,
+        };
+        assert!(new.exec(&mut config).is_err());
+        assert!(!utils::dir::theme(&config).unwrap().join("mine").exists());
+        assert!(Theme::find(&mut config, "mine").is_none());
+    }
+
     #[test]
     fn test_name_validation() {
         assert!(New::validate_name("test/theme").is_err());
@@ -0,0 +1,109 @@
+use crate::errors::{friendly_message, LeftError};
+use crate::models::{Config, Theme};
+use crate::utils::palette::{self, Occurrence, Rgb};
+use clap::Parser;
+use colored::Colorize;
+
+/* This function lists the colours used by an installed theme, as found in its
+   theme.ron / theme.toml and common bar configuration files.
+*/
+
+#[derive(Parser, Debug)]
+pub struct Palette {
+    /// Name of an installed theme
+    pub name: String,
+}
+
+impl Palette {
+    /// # Errors
+    ///
+    /// Will error if the theme is not installed.
+    /// Will error if a file of the theme cannot be read.
+    pub fn exec(&self, config: &mut Config) -> Result<(), LeftError> {
+        let dir = installed_theme_dir(config, &self.name)?;
+        let found = palette::scan(&dir)?;
+        if found.is_empty() {
+            println!("{}", "No colours found.".bright_blue().bold());
+            return Ok(());
+        }
+        for (color, uses) in group(&found) {
+            let Rgb(r, g, b) = color;
+            println!(
+                "{} {} ({} use(s))",
+                "    ".on_truecolor(r, g, b),
+                color.to_string().bold(),
+                uses.len()
+            );
+            for occurrence in uses {
+                println!(
+                    "    {}:{}  {}",
+                    occurrence.file.display(),
+                    occurrence.line,
+                    occurrence.text.dimmed()
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the directory holding the files of an installed theme, with
+/// `relative_directory` applied.
+///
+/// # Errors
+///
+/// Will error if no theme of that name is installed.
+pub(crate) fn installed_theme_dir(
+    config: &mut Config,
+    name: &str,
+) -> Result<std::path::PathBuf, LeftError> {
+    let theme = Theme::find_installed(config, name).ok_or_else(|| {
+        friendly_message(&format!(
+            "{name} is not installed. Try `leftwm-theme install \"{name}\"` first."
+        ))
+    })?;
+    let mut dir = theme.directory.clone().unwrap_or_default();
+    if let Some(rel_dir) = theme.relative_directory() {
+        dir.push(rel_dir);
+    }
+    Ok(dir)
+}
+
+// Groups occurrences by colour, in order of first appearance.
+fn group(found: &[Occurrence]) -> Vec<(Rgb, Vec<&Occurrence>)> {
+    let mut groups: Vec<(Rgb, Vec<&Occurrence>)> = Vec::new();
+    for occurrence in found {
+        match groups
+            .iter_mut()
+            .find(|(color, _)| *color == occurrence.color)
+        {
+            Some((_, uses)) => uses.push(occurrence),
+            None => groups.push((occurrence.color, vec![occurrence])),
+        }
+    }
+    groups
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_group_keeps_first_appearance_order() {
+        let occurrence = |color, line| Occurrence {
+            color,
+            file: PathBuf::from("theme.ron"),
+            line,
+            text: String::new(),
+        };
+        let red = Rgb(255, 0, 0);
+        let blue = Rgb(0, 0, 255);
+        let found = vec![occurrence(red, 1), occurrence(blue, 2), occurrence(red, 3)];
+        let groups = group(&found);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].0, red);
+        assert_eq!(groups[0].1.len(), 2);
+        assert_eq!(groups[1].0, blue);
+    }
+}
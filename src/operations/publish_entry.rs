@@ -0,0 +1,282 @@
+use crate::errors::{friendly_message, LeftError};
+use crate::models::{Config, DependencyL, TempThemes, Theme};
+use crate::utils::{diff, scripts};
+use clap::Parser;
+use colored::Colorize;
+use git2::Repository;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/* This function prints the `[[theme]]` block for a theme repository, ready to
+   paste into a known.toml index. It is built from the repository's remote and
+   HEAD commit, its index-entry.toml if any, and the programs its scripts run.
+*/
+
+#[derive(Parser, Debug)]
+pub struct PublishEntry {
+    /// Directory of the theme; defaults to the current directory
+    pub path: Option<PathBuf>,
+
+    /// Compare the entry with the one for the same theme in this known.toml
+    #[clap(long, value_name = "KNOWN_TOML")]
+    pub check_against: Option<PathBuf>,
+}
+
+// The shape of a known.toml file, to serialize a single `[[theme]]` block.
+#[derive(Serialize)]
+struct Index<'a> {
+    theme: [&'a Theme; 1],
+}
+
+impl PublishEntry {
+    /// # Errors
+    ///
+    /// Will error if the path is not inside a git repository with a commit and a remote.
+    /// Will error if the `--check-against` file cannot be read or parsed.
+    /// Will error if the entry differs from the one in the `--check-against` file.
+    pub fn exec(&self, _config: &mut Config) -> Result<(), LeftError> {
+        let path = self.path.clone().unwrap_or_else(|| PathBuf::from("."));
+        let entry = entry(&path, None)?;
+        let generated = to_toml(&entry)?;
+
+        let Some(known) = &self.check_against else {
+            print!("{generated}");
+            return Ok(());
+        };
+        let index = TempThemes::parse(&fs::read_to_string(known)?)?;
+        let Some(existing) = index.theme.into_iter().find(|theme| {
+            theme.name == entry.name
+                || (theme.repository.is_some() && theme.repository == entry.repository)
+        }) else {
+            println!(
+                "{}",
+                format!(
+                    "{} has no entry for {}; this would be a new entry:",
+                    known.display(),
+                    &entry.name
+                )
+                .bright_yellow()
+                .bold()
+            );
+            print!("{generated}");
+            return Ok(());
+        };
+        let existing = to_toml(&existing)?;
+        if existing == generated {
+            println!(
+                "{}",
+                format!("The entry in {} is up to date.", known.display())
+                    .bright_green()
+                    .bold()
+            );
+            return Ok(());
+        }
+        diff::print(&existing, &generated);
+        Err(friendly_message(&format!(
+            "The entry for {} in {} differs from the repository.",
+            &entry.name,
+            known.display()
+        )))
+    }
+}
+
+/// Builds the index entry for the theme whose files are at `path`. Unless
+/// `repository` is given, the entry points to the repository's remote.
+///
+/// # Errors
+///
+/// Will error if `path` is not inside a git repository with a commit.
+/// Will error if no `repository` is given and the repository has no remote.
+pub(crate) fn entry(path: &Path, repository: Option<&str>) -> Result<Theme, LeftError> {
+    let repo = Repository::discover(path)?;
+    let workdir = repo
+        .workdir()
+        .map(fs::canonicalize)
+        .transpose()?
+        .ok_or_else(|| friendly_message("The repository has no working directory."))?;
+    let dir = fs::canonicalize(path)?;
+
+    let metadata = [dir.as_path(), workdir.as_path()]
+        .iter()
+        .find_map(|dir| Theme::from_index_entry(dir));
+    let mut entry = metadata.clone().unwrap_or_else(|| {
+        let name = dir
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        Theme::new(&name, None, None)
+    });
+    entry.directory = None;
+    entry.current = None;
+
+    // The theme files are where `path` points, unless it is the repository
+    // root and the metadata already says where they are.
+    let relative = dir.strip_prefix(&workdir).unwrap_or(Path::new(""));
+    if relative != Path::new("") {
+        entry.set_relative_directory(Some(relative.display().to_string()));
+    }
+    let theme_dir = match entry.relative_directory() {
+        Some(rel_dir) => workdir.join(rel_dir),
+        None => workdir.clone(),
+    };
+
+    entry.repository = Some(match repository {
+        Some(repository) => repository.to_string(),
+        None => remote_url(&repo)?,
+    });
+    entry.commit = Some(repo.head()?.peel_to_commit()?.id().to_string());
+
+    let mut dependencies = entry.dependencies.take().unwrap_or_default();
+    let declared: BTreeSet<String> = dependencies.iter().map(|d| d.program.clone()).collect();
+    let mut detected = BTreeSet::new();
+    for script in scripts::find(&theme_dir) {
+        detected.extend(scripts::called_programs(
+            &fs::read_to_string(script).unwrap_or_default(),
+        ));
+    }
+    dependencies.extend(
+        detected
+            .into_iter()
+            .filter(|program| !declared.contains(program))
+            .map(|program| DependencyL {
+                program,
+                optional: None,
+                packages: None,
+            }),
+    );
+    if !dependencies.is_empty() {
+        entry.dependencies = Some(dependencies);
+    }
+    Ok(entry)
+}
+
+// The URL of the `origin` remote, or of the only remote, made public.
+fn remote_url(repo: &Repository) -> Result<String, LeftError> {
+    let remote = if let Ok(remote) = repo.find_remote("origin") {
+        remote
+    } else {
+        let names = repo.remotes()?;
+        let name = names.iter().flatten().next().ok_or_else(|| {
+            friendly_message("The repository has no remote. Push it somewhere public first.")
+        })?;
+        repo.find_remote(name)?
+    };
+    let url = remote
+        .url()
+        .ok_or_else(|| friendly_message("The remote URL is not valid UTF-8."))?;
+    Ok(public_url(url))
+}
+
+// Users install themes without credentials, so SSH remotes are turned into
+// their HTTPS equivalent, e.g. `git@github.com:me/theme.git`.
+fn public_url(url: &str) -> String {
+    if let Some(rest) = url.strip_prefix("ssh://") {
+        let rest = rest.split_once('@').map_or(rest, |(_, host)| host);
+        let (host, path) = rest.split_once('/').unwrap_or((rest, ""));
+        // The SSH port means nothing to the HTTPS server.
+        let host = host.split_once(':').map_or(host, |(host, _)| host);
+        return format!("https://{host}/{path}");
+    }
+    if !url.contains("://") {
+        if let Some((user_host, path)) = url.split_once(':') {
+            let host = user_host
+                .split_once('@')
+                .map_or(user_host, |(_, host)| host);
+            return format!("https://{host}/{path}");
+        }
+    }
+    url.to_string()
+}
+
+/// Serializes an entry as a known.toml `[[theme]]` block.
+///
+/// # Errors
+///
+/// Will error if the entry cannot be serialized.
+pub(crate) fn to_toml(theme: &Theme) -> Result<String, LeftError> {
+    let mut theme = theme.clone();
+    theme.directory = None;
+    theme.current = None;
+    Ok(toml::to_string(&Index { theme: [&theme] })?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::models::INDEX_ENTRY_FILE;
+    use crate::utils::git;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn test_public_url() {
+        assert_eq!(
+            public_url("git@github.com:me/theme.git"),
+            "https://github.com/me/theme.git"
+        );
+        assert_eq!(
+            public_url("ssh://git@gitlab.com/me/theme.git"),
+            "https://gitlab.com/me/theme.git"
+        );
+        assert_eq!(
+            public_url("ssh://git@example.com:2222/me/theme"),
+            "https://example.com/me/theme"
+        );
+        assert_eq!(
+            public_url("https://github.com/me/theme"),
+            "https://github.com/me/theme"
+        );
+    }
+
+    #[test]
+    fn test_entry() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let root = tmpdir.path().join("forest");
+        let dir = root.join("theme");
+        fs::create_dir_all(&dir).unwrap();
+        let repo = Repository::init(&root).unwrap();
+        repo.remote("origin", "git@github.com:me/forest.git")
+            .unwrap();
+        fs::write(
+            root.join(INDEX_ENTRY_FILE),
+            "[[theme]]\nname = \"Forest\"\ndescription = \"Green\"\nversion = \"1.0.0\"\ndependencies = [{ program = \"polybar\", optional = false, package = \"polybar-git\" }]\n",
+        )
+        .unwrap();
+        let up = dir.join("up");
+        fs::write(&up, "#!/bin/sh\npolybar &\nfeh --bg-fill a.png\n").unwrap();
+        fs::set_permissions(&up, fs::Permissions::from_mode(0o755)).unwrap();
+        let commit = git::commit_files(&root, &[&up], "Initial commit").unwrap();
+
+        let entry = entry(&dir, None).unwrap();
+        assert_eq!(
+            to_toml(&entry).unwrap(),
+            format!(
+                r#"[[theme]]
+name = "Forest"
+description = "Green"
+repository = "https://github.com/me/forest.git"
+commit = "{commit}"
+version = "1.0.0"
+relative_directory = "theme"
+
+[[theme.dependencies]]
+program = "polybar"
+optional = false
+
+[theme.dependencies.packages]
+"*" = "polybar-git"
+
+[[theme.dependencies]]
+program = "feh"
+"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_entry_without_remote() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        Repository::init(tmpdir.path()).unwrap();
+        assert!(entry(tmpdir.path(), None).is_err());
+    }
+}
@@ -0,0 +1,195 @@
+use crate::errors::{friendly_message, LeftError};
+use crate::models::config::LOCAL_REPO_NAME;
+use crate::models::{Config, Theme, ThemeFile, THEMES_DIR};
+use crate::operations::new::New;
+use crate::operations::palette::installed_theme_dir;
+use crate::utils;
+use crate::utils::palette::{self, UserPalette};
+use clap::Parser;
+use colored::Colorize;
+use git2::Repository;
+use log::{trace, warn};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/* This function creates a new LOCAL theme from an installed theme, with its
+   colours replaced by those of a base16 scheme or a pywal colors.json.
+*/
+
+#[derive(Parser, Debug)]
+pub struct Recolor {
+    /// Name of the installed theme to start from
+    pub name: String,
+
+    /// Palette to apply: a base16 scheme (YAML) or a pywal `colors.json`
+    pub palette: PathBuf,
+
+    /// Name of the new theme; defaults to `<theme>-<palette file name>`
+    #[clap(short = 'o', long)]
+    pub output: Option<String>,
+}
+
+impl Recolor {
+    /// # Errors
+    ///
+    /// Will error if the theme is not installed or the palette cannot be read.
+    /// Will error if the new name is not valid or already taken.
+    /// Will error if the new theme cannot be written, or config cannot be saved.
+    pub fn exec(&self, config: &mut Config) -> Result<(), LeftError> {
+        let source = installed_theme_dir(config, &self.name)?;
+        let user_palette = UserPalette::load(&self.palette)?;
+        let new_name = self.output.clone().unwrap_or_else(|| self.default_name());
+        New::validate_name(&new_name)?;
+        if Theme::find(config, &new_name).is_some() {
+            return Err(friendly_message(&format!(
+                "A theme named {new_name} already exists. Choose another name with --output."
+            )));
+        }
+        let target = config.get_config_dir()?.join(THEMES_DIR).join(&new_name);
+        if target.exists() {
+            return Err(friendly_message(&format!(
+                "{} already exists. Choose another name with --output.",
+                target.display()
+            )));
+        }
+
+        recolor_theme(&source, &target, &user_palette)?;
+        Repository::init(&target)?;
+        Config::update_or_append(
+            config,
+            &Theme::new(
+                &new_name,
+                Some(format!(
+                    "{} recoloured with {}",
+                    &self.name,
+                    self.palette.display()
+                )),
+                Some(target.clone()),
+            ),
+            (&String::from("localhost"), &String::from(LOCAL_REPO_NAME)),
+        );
+        Config::save(config)?;
+        println!(
+            "{} {} {} {}",
+            "Theme".green().bold(),
+            &new_name.red().bold(),
+            "created successfully in".green().bold(),
+            target.display().to_string().red().bold()
+        );
+        println!("Apply it with `leftwm-theme apply {new_name}`.");
+        Ok(())
+    }
+
+    // `<theme>-<palette file name>`, reduced to the characters allowed in names.
+    fn default_name(&self) -> String {
+        let stem = self
+            .palette
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        format!("{}-{stem}", &self.name)
+            .to_lowercase()
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || "@._+-".contains(c) {
+                    c
+                } else {
+                    '-'
+                }
+            })
+            .collect::<String>()
+            .trim_start_matches(['-', '.'])
+            .to_string()
+    }
+}
+
+// Copies a theme to `target`, replacing its colours with the palette's.
+fn recolor_theme(
+    source: &Path,
+    target: &Path,
+    user_palette: &UserPalette,
+) -> Result<(), LeftError> {
+    let found = palette::scan(source)?;
+    let settings = ["theme.ron", "theme.toml"]
+        .iter()
+        .map(|file| source.join(file))
+        .find(|path| path.is_file())
+        .and_then(|path| match ThemeFile::load(&path) {
+            Ok(settings) => Some(settings),
+            Err(err) => {
+                warn!(
+                    "Could not parse {}, matching colours by similarity only: {err}",
+                    path.display()
+                );
+                None
+            }
+        })
+        .unwrap_or_default();
+    let colors: Vec<palette::Rgb> = found.iter().map(|o| o.color).collect();
+    let mapping = user_palette.mapping(&palette::roles(&settings), &colors);
+
+    utils::copy::tree(source, target)?.report();
+    let files: BTreeSet<&PathBuf> = found.iter().map(|o| &o.file).collect();
+    for file in files {
+        trace!("Recolouring {}", file.display());
+        let contents = fs::read_to_string(source.join(file))?;
+        fs::write(
+            target.join(file),
+            palette::recolor(&contents, file, &mapping),
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const BASE16: &str = "base00: \"181818\"\nbase01: \"282828\"\nbase02: \"383838\"\nbase03: \"585858\"\nbase04: \"b8b8b8\"\nbase05: \"d8d8d8\"\nbase06: \"e8e8e8\"\nbase07: \"f8f8f8\"\nbase08: \"ab4642\"\nbase09: \"dc9656\"\nbase0A: \"f7ca88\"\nbase0B: \"a1b56c\"\nbase0C: \"86c1b9\"\nbase0D: \"7cafc2\"\nbase0E: \"ba8baf\"\nbase0F: \"a16946\"\n";
+
+    #[test]
+    fn test_default_name() {
+        let recolor = Recolor {
+            name: String::from("Orange Forest"),
+            palette: PathBuf::from("/home/me/.cache/wal/colors.json"),
+            output: None,
+        };
+        assert_eq!(recolor.default_name(), "orange-forest-colors");
+        assert!(New::validate_name(&recolor.default_name()).is_ok());
+    }
+
+    #[test]
+    fn test_recolor_theme() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let source = tmpdir.path().join("source");
+        let target = tmpdir.path().join("target");
+        fs::create_dir_all(source.join(".git")).unwrap();
+        fs::create_dir_all(source.join("polybar")).unwrap();
+        fs::write(
+            source.join("theme.ron"),
+            "#![enable(implicit_some)]\n(focused_border_color: \"#FFB53A\", background_color: \"#000000\")",
+        )
+        .unwrap();
+        fs::write(source.join("polybar").join("config"), "primary = #ffb53a\n").unwrap();
+        fs::write(source.join("up"), "#!/bin/sh\n").unwrap();
+
+        let user_palette = UserPalette::from_base16(BASE16).unwrap();
+        recolor_theme(&source, &target, &user_palette).unwrap();
+        assert_eq!(
+            fs::read_to_string(target.join("theme.ron")).unwrap(),
+            "#![enable(implicit_some)]\n(focused_border_color: \"#7CAFC2\", background_color: \"#181818\")"
+        );
+        assert_eq!(
+            fs::read_to_string(target.join("polybar").join("config")).unwrap(),
+            "primary = #7cafc2\n"
+        );
+        assert!(target.join("up").exists());
+        assert!(!target.join(".git").exists());
+        // The installed theme is left alone.
+        assert_eq!(
+            fs::read_to_string(source.join("polybar").join("config")).unwrap(),
+            "primary = #ffb53a\n"
+        );
+    }
+}
@@ -0,0 +1,87 @@
+use crate::errors::LeftError;
+use crate::models::Config;
+use crate::operations::Update;
+use clap::Parser;
+use colored::Colorize;
+use log::warn;
+
+/* This function recreates themes.toml, e.g. when it became invalid: the
+   default repositories are fetched again and the installed themes re-imported,
+   after backing up the old file.
+*/
+
+#[derive(Parser, Debug)]
+pub struct Repair {
+    /// Don't fetch the theme repositories; installed themes are kept as local themes
+    #[clap(long)]
+    pub offline: bool,
+}
+
+impl Repair {
+    /// # Errors
+    ///
+    /// Will error if the old themes.toml cannot be backed up.
+    /// Will error if the installed themes cannot be listed.
+    /// Will error if config cannot be saved.
+    pub fn exec(&self, config: &mut Config) -> Result<(), LeftError> {
+        if let Some(backup) = config.backup()? {
+            println!(
+                "{}",
+                format!("Backed up themes.toml to {}.", backup.display()).bright_blue()
+            );
+        }
+        let mut repaired = Config::new(config.config_dir.clone()).default();
+        if self.offline {
+            repaired.update_local_repo()?;
+        } else if let Err(err) = Update::update_repos(&mut repaired) {
+            warn!("Could not fetch the theme repositories: {err}");
+            repaired.update_local_repo()?;
+        }
+        Config::save(&repaired)?;
+        *config = repaired;
+
+        let installed = config
+            .themes(false)
+            .iter()
+            .filter(|theme| theme.directory.is_some())
+            .count();
+        println!(
+            "{}",
+            format!("Recreated themes.toml with {installed} installed theme(s).")
+                .bright_green()
+                .bold()
+        );
+        println!("Add back any repositories you had added to it, then run `leftwm-theme update`.");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::models::THEMES_DIR;
+    use std::fs;
+
+    #[test]
+    fn test_repair_offline() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(tmpdir.path().join(THEMES_DIR).join("forest")).unwrap();
+        fs::write(tmpdir.path().join("themes.toml"), "repos = [").unwrap();
+
+        let mut config = Config::new(Some(tmpdir.path().to_path_buf()));
+        assert!(config.load().is_err());
+        Repair { offline: true }.exec(&mut config).unwrap();
+
+        let loaded = Config::new(Some(tmpdir.path().to_path_buf()))
+            .load()
+            .unwrap();
+        assert_eq!(loaded.repos.len(), 2);
+        let forest = loaded
+            .repos
+            .iter()
+            .flat_map(|repo| repo.themes.iter())
+            .find(|theme| theme.name == "forest")
+            .unwrap();
+        assert!(forest.directory.is_some());
+    }
+}
@@ -19,7 +19,7 @@ impl Status {
         println!(
             "{} {}",
             "Your LeftWM version is".bright_blue().bold(),
-            LeftWm::get()?.version.bright_green().bold()
+            LeftWm::get().version_string().bright_green().bold()
         );
         let mut current = 0;
         let mut installed = 0;
@@ -33,11 +33,19 @@ impl Status {
                     "located in the".bright_blue().bold(),
                     theme
                         .source
+                        .clone()
                         .unwrap_or_else(|| "unknown".to_string())
                         .bright_magenta()
                         .bold(),
                     "repo".bright_blue().bold()
                 );
+                if let Some(notice) = theme.notice() {
+                    println!(
+                        "{} {}",
+                        "Your current theme was".bright_yellow().bold(),
+                        notice.bright_yellow().bold()
+                    );
+                }
             }
             if theme.directory.is_some() {
                 installed += 1;
@@ -0,0 +1,706 @@
+use super::upgrade::fetch_origin_main;
+use crate::errors::{friendly_message, LeftError};
+use crate::models::config::LOCAL_REPO_NAME;
+use crate::models::wanted::WANTED_FILE;
+use crate::models::{
+    Config, LeftWm, Repo, TempThemes, Theme, Wanted, WantedRepo, WantedTheme, THEMES_DIR,
+};
+use crate::operations::{Apply, Uninstall};
+use crate::utils;
+use crate::utils::git;
+use crate::utils::read::yes_or_no;
+use crate::utils::versions::Compatibility;
+use clap::Parser;
+use colored::Colorize;
+use git2::build::RepoBuilder;
+use git2::{Oid, Repository};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/* This function makes the installed themes match the wanted ones, declared in
+   a manifest (themes-wanted.toml) or a lock file written by `leftwm-theme lock`.
+   It prints a plan of the changes needed, then carries it out: installing,
+   upgrading or checking out themes, applying the current one and uninstalling
+   themes no longer wanted. Planning only reads: repositories are fetched and
+   branches looked up, but nothing is written until the plan is carried out.
+*/
+
+// The branch followed by themes which name none, as `upgrade` does.
+const DEFAULT_BRANCH: &str = "master";
+
+#[derive(Parser, Debug)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct SyncThemes {
+    /// Manifest of wanted themes, or a lock file if it ends in `.lock`;
+    /// defaults to themes-wanted.toml in the config directory
+    pub file: Option<PathBuf>,
+
+    /// Uninstall themes which are not in the manifest or lock file
+    #[clap(long)]
+    pub prune: bool,
+
+    /// Only print what would be done
+    #[clap(long)]
+    pub dry_run: bool,
+
+    /// Don't ask for confirmation before carrying out the plan
+    #[clap(long)]
+    pub noconfirm: bool,
+
+    /// Don't restart leftwm-worker
+    #[clap(short = 'n', long)]
+    pub no_reset: bool,
+}
+
+// A wanted theme, resolved to where it is cloned from and the commit it
+// should be at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Target {
+    name: String,
+    repo: String,
+    repo_url: String,
+    // None for local themes, which cannot be cloned
+    repository: Option<String>,
+    branch: Option<String>,
+    // None to follow the branch
+    commit: Option<String>,
+    relative_directory: Option<String>,
+}
+
+/// A change needed to reach the wanted set of themes.
+#[derive(Debug, PartialEq, Eq)]
+enum Step {
+    AddRepo(WantedRepo),
+    Rename {
+        repo: String,
+        from: String,
+        to: String,
+    },
+    Install(Target),
+    Upgrade {
+        name: String,
+        branch: String,
+        commit: Oid,
+    },
+    Checkout {
+        name: String,
+        commit: String,
+    },
+    Apply(String),
+    Uninstall(String),
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Step::AddRepo(repo) => write!(f, "add repository {} ({})", repo.name, repo.url),
+            Step::Rename { from, to, .. } => write!(f, "rename {from} to {to}"),
+            Step::Install(target) => {
+                write!(f, "install {}/{}", target.repo, target.name)?;
+                match &target.commit {
+                    Some(commit) => write!(f, " at {}", short(commit)),
+                    None => Ok(()),
+                }
+            }
+            Step::Upgrade { name, commit, .. } => {
+                write!(f, "upgrade {name} to {}", short(&commit.to_string()))
+            }
+            Step::Checkout { name, commit } => write!(f, "check out {name} at {}", short(commit)),
+            Step::Apply(name) => write!(f, "apply {name}"),
+            Step::Uninstall(name) => write!(f, "uninstall {name}"),
+        }
+    }
+}
+
+impl SyncThemes {
+    /// # Errors
+    ///
+    /// Will error if the manifest or lock file cannot be read.
+    /// Will error if a wanted theme cannot be found in any repository.
+    /// Will error if a theme cannot be cloned or checked out.
+    /// Will error if a theme cannot be uninstalled or applied, or config cannot be saved.
+    pub fn exec(&self, config: &mut Config) -> Result<(), LeftError> {
+        let path = match &self.file {
+            Some(file) => file.clone(),
+            None => config.get_config_dir()?.join(WANTED_FILE),
+        };
+        let mut wanted = Wanted::load(&path)?;
+        if self.prune {
+            wanted.keep_unlisted = false;
+        }
+
+        // Plan against a copy, so nothing changes unless the plan is carried out.
+        let mut target = config.clone();
+        let mut steps = add_repos(&mut target, &wanted);
+        if wanted.theme.iter().any(|theme| theme.repository.is_none()) {
+            steps.extend(refresh_repos(&mut target)?);
+        }
+        steps.extend(plan(&mut target, &wanted)?);
+        if wanted.keep_unlisted {
+            for name in unlisted(&target, &wanted) {
+                println!(
+                    "{}",
+                    format!("{name} is installed but not wanted; use --prune to uninstall it.")
+                        .bright_yellow()
+                );
+            }
+        }
+        if steps.is_empty() {
+            println!("{}", "Themes are in sync.".bright_green().bold());
+            return Ok(());
+        }
+        println!("{}", "Plan:".bright_blue().bold());
+        for step in &steps {
+            println!("    {step}");
+        }
+        if self.dry_run {
+            return Ok(());
+        }
+        if !self.noconfirm && !yes_or_no("Carry out this plan?") {
+            println!("{}", "No actions to take. Exiting . . . ".yellow().bold());
+            return Ok(());
+        }
+
+        *config = target;
+        let themes_dir = config.get_config_dir()?.join(THEMES_DIR);
+        for step in steps {
+            println!("{}", format!("Running: {step}").bright_blue().bold());
+            match step {
+                // The repository was added to the config while planning.
+                Step::AddRepo(_) => {}
+                Step::Rename { repo, to, .. } => {
+                    let config_dir = config.get_config_dir()?;
+                    let owned = config
+                        .repos
+                        .iter()
+                        .find(|r| r.name == repo)
+                        .map(|r| r.themes.clone())
+                        .unwrap_or_default();
+                    let theme = Theme::find_mut(config, &to, &repo)
+                        .ok_or_else(|| friendly_message("Theme not installed"))?;
+                    Repo::rename_installed(&config_dir, theme, &owned)?;
+                }
+                Step::Install(target) => install(config, &themes_dir, &target)?,
+                Step::Upgrade { name, branch, .. } => upgrade(config, &name, &branch)?,
+                Step::Checkout { name, commit } => {
+                    git::checkout_commit(&open(config, &name)?, &commit)?;
+                    refresh_current(config, &name)?;
+                }
+                Step::Apply(name) => Apply {
+                    name,
+                    no_reset: self.no_reset,
+                    override_checks: false,
+                }
+                .exec(config)?,
+                Step::Uninstall(name) => Uninstall {
+                    name,
+                    noconfirm: true,
+                }
+                .exec(config)?,
+            }
+        }
+        Config::save(config)?;
+        println!("{}", "Themes are in sync.".bright_green().bold());
+        Ok(())
+    }
+}
+
+// Adds the wanted repositories missing from the config.
+fn add_repos(config: &mut Config, wanted: &Wanted) -> Vec<Step> {
+    let mut steps = Vec::new();
+    for wanted_repo in &wanted.repos {
+        if wanted_repo.name == LOCAL_REPO_NAME
+            || config
+                .repos
+                .iter()
+                .any(|repo| repo.name == wanted_repo.name)
+        {
+            continue;
+        }
+        config.repos.push(Repo {
+            url: wanted_repo.url.clone(),
+            name: wanted_repo.name.clone(),
+            ..Repo::default()
+        });
+        steps.push(Step::AddRepo(wanted_repo.clone()));
+    }
+    steps
+}
+
+// Refreshes the repositories from their indexes, as `update` does, but leaves
+// the installed themes alone; those renamed upstream are moved by a step.
+fn refresh_repos(config: &mut Config) -> Result<Vec<Step>, LeftError> {
+    println!("{}", "Fetching themes . . . ".bright_blue().bold());
+    let config_dir = config.get_config_dir()?;
+    let mut steps = Vec::new();
+    for repo in &mut config.repos {
+        if repo.name == LOCAL_REPO_NAME {
+            continue;
+        }
+        let content = utils::fetch::text(&repo.url)?;
+        if content.is_empty() {
+            continue;
+        }
+        for (from, to) in repo.preview(TempThemes::parse(&content)?, &config_dir)? {
+            steps.push(Step::Rename {
+                repo: repo.name.clone(),
+                from,
+                to,
+            });
+        }
+    }
+    config.update_local_repo()?;
+    Ok(steps)
+}
+
+// The steps needed to install, upgrade and apply the wanted themes, and to
+// uninstall the others unless they are kept.
+fn plan(config: &mut Config, wanted: &Wanted) -> Result<Vec<Step>, LeftError> {
+    let mut steps = Vec::new();
+    for theme in &wanted.theme {
+        let target = resolve(config, wanted, theme)?;
+        let installed = Theme::find_installed(config, &target.name).and_then(|t| t.directory);
+        let Some(directory) = installed else {
+            if target.repository.is_none() {
+                return Err(friendly_message(&format!(
+                    "{} is a local theme which is not installed.",
+                    target.name
+                )));
+            }
+            steps.push(Step::Install(target));
+            continue;
+        };
+        if let Some(commit) = target.commit {
+            if git::head_commit(&directory).ok().as_ref() != Some(&commit) {
+                steps.push(Step::Checkout {
+                    name: target.name,
+                    commit,
+                });
+            }
+            continue;
+        }
+        if target.repo == LOCAL_REPO_NAME {
+            continue;
+        }
+        let branch = target
+            .branch
+            .unwrap_or_else(|| String::from(DEFAULT_BRANCH));
+        match newer_tip(&directory, &branch) {
+            Ok(None) => {}
+            Ok(Some(_)) if !compatible(config, &target.name) => {
+                println!(
+                    "{}",
+                    format!(
+                        "Not upgrading {}: it is not compatible with your version of LeftWM.",
+                        target.name
+                    )
+                    .bright_yellow()
+                );
+                if let Some(note) = LeftWm::get().assumed_note() {
+                    println!("{}", note.bright_yellow());
+                }
+            }
+            Ok(Some(tip)) => steps.push(Step::Upgrade {
+                name: target.name,
+                branch,
+                commit: tip,
+            }),
+            Err(err) => println!(
+                "{}",
+                format!(
+                    "Could not check {} for updates: {}",
+                    target.name,
+                    err.message()
+                )
+                .bright_yellow()
+            ),
+        }
+    }
+    if let Some(current) = &wanted.current {
+        let is_current =
+            Theme::find_installed(config, current).is_some_and(|theme| theme.current == Some(true));
+        if !is_current {
+            steps.push(Step::Apply(current.clone()));
+        }
+    }
+    if !wanted.keep_unlisted {
+        steps.extend(unlisted(config, wanted).into_iter().map(Step::Uninstall));
+    }
+    Ok(steps)
+}
+
+// The tip of the branch on origin, looked up without fetching, if the theme
+// is not there yet. Local commits on top of the tip are not an upgrade.
+fn newer_tip(directory: &Path, branch: &str) -> Result<Option<Oid>, git2::Error> {
+    let repo = Repository::open(directory)?;
+    let tip = git::branch_tip(&repo, branch)?;
+    let head = repo.head()?.peel_to_commit()?.id();
+    if head == tip || repo.graph_descendant_of(head, tip).unwrap_or(false) {
+        return Ok(None);
+    }
+    Ok(Some(tip))
+}
+
+// Whether the theme's index entry allows the installed version of LeftWM.
+fn compatible(config: &mut Config, name: &str) -> bool {
+    let versions = Theme::find_installed(config, name)
+        .and_then(|theme| theme.leftwm_versions)
+        .unwrap_or_else(|| String::from("*"));
+    matches!(
+        utils::versions::check(&versions),
+        Ok(Compatibility::Compatible | Compatibility::Unknown)
+    )
+}
+
+// Finds where a wanted theme comes from: its own repository, or the entry of
+// a repository listing it.
+fn resolve(config: &Config, wanted: &Wanted, theme: &WantedTheme) -> Result<Target, LeftError> {
+    if let Some(repository) = &theme.repository {
+        let repo = theme
+            .repo
+            .clone()
+            .unwrap_or_else(|| String::from(LOCAL_REPO_NAME));
+        let repo_url = config
+            .repos
+            .iter()
+            .map(|repo| (&repo.name, &repo.url))
+            .chain(wanted.repos.iter().map(|repo| (&repo.name, &repo.url)))
+            .find(|(name, _)| **name == repo)
+            .map_or_else(|| String::from("localhost"), |(_, url)| url.clone());
+        return Ok(Target {
+            name: theme.name.clone(),
+            repo,
+            repo_url,
+            repository: Some(repository.clone()),
+            branch: None,
+            commit: theme.commit.clone(),
+            relative_directory: theme.relative_directory.clone(),
+        });
+    }
+
+    let found: Vec<(&Repo, &Theme)> = config
+        .repos
+        .iter()
+        .filter(|repo| theme.repo.iter().all(|name| *name == repo.name))
+        .flat_map(|repo| {
+            repo.themes
+                .iter()
+                .filter(|listed| listed.name == theme.name)
+                .map(move |listed| (repo, listed))
+        })
+        .collect();
+    let (repo, listed) = match found.as_slice() {
+        [] => {
+            return Err(friendly_message(&format!(
+                "{} is not in any repository; add its repository under [[repos]], or give its repository and commit.",
+                theme.name
+            )))
+        }
+        [one] => *one,
+        _ => {
+            return Err(friendly_message(&format!(
+                "{} is in several repositories; choose one with `repo`.",
+                theme.name
+            )))
+        }
+    };
+    if listed.removed_upstream == Some(true) && listed.directory.is_none() {
+        return Err(friendly_message(&format!(
+            "{} was removed from {}.",
+            theme.name, repo.name
+        )));
+    }
+    Ok(Target {
+        name: theme.name.clone(),
+        repo: repo.name.clone(),
+        repo_url: repo.url.clone(),
+        repository: listed.repository.clone(),
+        branch: listed.branch.clone(),
+        commit: theme
+            .commit
+            .clone()
+            .or_else(|| listed.commit.clone().filter(|commit| commit != "*")),
+        relative_directory: listed.relative_directory(),
+    })
+}
+
+// The installed themes which are not wanted. Local themes are never listed:
+// they cannot be installed again.
+fn unlisted(config: &Config, wanted: &Wanted) -> Vec<String> {
+    config
+        .repos
+        .iter()
+        .filter(|repo| repo.name != LOCAL_REPO_NAME)
+        .flat_map(|repo| repo.themes.iter())
+        .filter(|theme| theme.directory.is_some())
+        .filter(|theme| !wanted.theme.iter().any(|other| other.name == theme.name))
+        .map(|theme| theme.name.clone())
+        .collect()
+}
+
+// Clones a theme, at its commit if pinned, and adds it to the config.
+fn install(config: &mut Config, themes_dir: &Path, target: &Target) -> Result<(), LeftError> {
+    let dir = themes_dir.join(&target.name);
+    if dir.exists() {
+        return Err(friendly_message(&format!(
+            "{} already exists, but is not a theme known to leftwm-theme.",
+            dir.display()
+        )));
+    }
+    let repository = target
+        .repository
+        .as_ref()
+        .ok_or_else(|| friendly_message("Repository information missing for theme"))?;
+    let mut builder = RepoBuilder::new();
+    if let Some(branch) = &target.branch {
+        builder.branch(branch);
+    }
+    let repo = builder.clone(repository, &dir)?;
+    if let Some(commit) = &target.commit {
+        git::checkout_commit(&repo, commit)?;
+    }
+
+    if let Some(listed) = Theme::find_mut(config, &target.name, &target.repo) {
+        listed.directory = Some(dir);
+        return Ok(());
+    }
+    let mut theme = Theme::new(&target.name, None, Some(dir));
+    theme.repository = Some(repository.clone());
+    theme.commit.clone_from(&target.commit);
+    theme.set_relative_directory(target.relative_directory.clone());
+    Config::update_or_append(config, &theme, (&target.repo_url, &target.repo));
+    Ok(())
+}
+
+// Merges the theme's branch from origin, as `upgrade` does. Local edits are
+// not overwritten: the theme must have no uncommitted changes.
+fn upgrade(config: &mut Config, name: &str, branch: &str) -> Result<(), LeftError> {
+    let repo = open(config, name)?;
+    git::ensure_clean(&repo)?;
+    fetch_origin_main(&repo, Some(branch))?;
+    refresh_current(config, name)
+}
+
+// After the current theme's files changed, regenerates its copy with overrides
+// and points `current` at it.
+fn refresh_current(config: &mut Config, name: &str) -> Result<(), LeftError> {
+    match Theme::find_installed(config, name) {
+        Some(theme) if theme.current == Some(true) => {
+            Repo::repoint_current(&config.get_config_dir()?, &theme)
+        }
+        _ => Ok(()),
+    }
+}
+
+// Opens the git repository of an installed theme.
+fn open(config: &mut Config, name: &str) -> Result<Repository, LeftError> {
+    let directory = Theme::find_installed(config, name)
+        .and_then(|theme| theme.directory)
+        .ok_or_else(|| friendly_message("Theme not installed"))?;
+    Ok(Repository::open(directory)?)
+}
+
+fn short(commit: &str) -> &str {
+    commit.get(..7).unwrap_or(commit)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::models::{LockFile, LockedTheme};
+    use std::fs;
+
+    // A theme repository with two commits, returned oldest first.
+    fn upstream(dir: &Path) -> (String, Oid, Oid) {
+        let repo = Repository::init(dir).unwrap();
+        fs::write(dir.join("up"), "#!/bin/sh").unwrap();
+        let first = git::commit_files(dir, &[&dir.join("up")], "Initial").unwrap();
+        fs::write(dir.join("down"), "#!/bin/sh").unwrap();
+        let second = git::commit_files(dir, &[&dir.join("down")], "Add down").unwrap();
+        let branch = repo.head().unwrap().shorthand().unwrap().to_string();
+        (branch, first, second)
+    }
+
+    fn wanted_theme(name: &str) -> WantedTheme {
+        WantedTheme {
+            name: String::from(name),
+            repo: None,
+            commit: None,
+            repository: None,
+            relative_directory: None,
+        }
+    }
+
+    #[test]
+    fn test_plan_manifest() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let source = tmpdir.path().join("upstream");
+        let (branch, first, second) = upstream(&source);
+        let repository = source.to_str().unwrap().to_string();
+        let themes_dir = tmpdir.path().join(THEMES_DIR);
+
+        // forest is installed at the first commit, and upstream moved on.
+        let forest_dir = themes_dir.join("forest");
+        let clone = Repository::clone(&repository, &forest_dir).unwrap();
+        git::checkout_commit(&clone, &first.to_string()).unwrap();
+        let mut forest = Theme::new("forest", None, Some(forest_dir));
+        forest.repository = Some(repository.clone());
+        forest.branch = Some(branch.clone());
+        let mut ocean = Theme::new("ocean", None, None);
+        ocean.repository = Some(repository.clone());
+        ocean.commit = Some(String::from("*"));
+        let extra = Theme::new("extra", None, Some(themes_dir.join("extra")));
+        let mut config = Config::new(Some(tmpdir.path().to_path_buf())).default();
+        config.repos[0].themes = vec![forest, ocean, extra];
+        config.repos[1]
+            .themes
+            .push(Theme::new("mine", None, Some(themes_dir.join("mine"))));
+
+        let mut pinned = wanted_theme("ocean");
+        pinned.commit = Some(first.to_string());
+        let mut wanted = Wanted {
+            current: Some(String::from("ocean")),
+            keep_unlisted: false,
+            repos: vec![WantedRepo {
+                name: String::from("friends"),
+                url: String::from("https://example.com/known.toml"),
+            }],
+            theme: vec![wanted_theme("forest"), pinned],
+        };
+
+        assert_eq!(
+            add_repos(&mut config, &wanted),
+            vec![Step::AddRepo(wanted.repos[0].clone())]
+        );
+        assert!(add_repos(&mut config, &wanted).is_empty());
+        let ocean_target = Target {
+            name: String::from("ocean"),
+            repo: config.repos[0].name.clone(),
+            repo_url: config.repos[0].url.clone(),
+            repository: Some(repository.clone()),
+            branch: None,
+            commit: Some(first.to_string()),
+            relative_directory: None,
+        };
+        assert_eq!(
+            plan(&mut config, &wanted).unwrap(),
+            vec![
+                Step::Upgrade {
+                    name: String::from("forest"),
+                    branch: branch.clone(),
+                    commit: second,
+                },
+                Step::Install(ocean_target.clone()),
+                Step::Apply(String::from("ocean")),
+                Step::Uninstall(String::from("extra")),
+            ]
+        );
+
+        install(&mut config, &themes_dir, &ocean_target).unwrap();
+        assert_eq!(
+            git::head_commit(&themes_dir.join("ocean")).unwrap(),
+            first.to_string()
+        );
+        // Upgrading merges the branch, but never over local edits.
+        let forest_file = themes_dir.join("forest").join("up");
+        fs::write(&forest_file, "#!/bin/bash").unwrap();
+        assert!(upgrade(&mut config, "forest", &branch).is_err());
+        assert_eq!(
+            git::head_commit(&themes_dir.join("forest")).unwrap(),
+            first.to_string()
+        );
+        fs::write(&forest_file, "#!/bin/sh").unwrap();
+        upgrade(&mut config, "forest", &branch).unwrap();
+        assert_eq!(
+            git::head_commit(&themes_dir.join("forest")).unwrap(),
+            second.to_string()
+        );
+        wanted.keep_unlisted = true;
+        assert_eq!(
+            plan(&mut config, &wanted).unwrap(),
+            vec![Step::Apply(String::from("ocean"))]
+        );
+
+        wanted.theme.push(wanted_theme("gone"));
+        assert!(plan(&mut config, &wanted).is_err());
+    }
+
+    #[test]
+    fn test_refresh_repos_leaves_installed_themes() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let themes_dir = tmpdir.path().join(THEMES_DIR);
+        fs::create_dir_all(themes_dir.join("theme-x")).unwrap();
+        let index = tmpdir.path().join("known.toml");
+        fs::write(
+            &index,
+            "[[theme]]\nname = \"theme-y\"\nrepository = \"https://example.com/theme-y\"\nrenamed_from = [\"theme-x\"]\n",
+        )
+        .unwrap();
+        let mut config = Config::new(Some(tmpdir.path().to_path_buf())).default();
+        config.repos[0].url = index.display().to_string();
+        config.repos[0].themes = vec![Theme::new(
+            "theme-x",
+            None,
+            Some(themes_dir.join("theme-x")),
+        )];
+        let repo = config.repos[0].name.clone();
+
+        let steps = refresh_repos(&mut config).unwrap();
+        assert_eq!(
+            steps,
+            vec![Step::Rename {
+                repo: repo.clone(),
+                from: String::from("theme-x"),
+                to: String::from("theme-y"),
+            }]
+        );
+        // Only the copy of the config knows about the rename so far.
+        assert!(themes_dir.join("theme-x").is_dir());
+        assert!(!themes_dir.join("theme-y").exists());
+        let theme = Theme::find_installed(&mut config, "theme-y").unwrap();
+        assert_eq!(theme.directory, Some(themes_dir.join("theme-x")));
+        assert!(Theme::find(&mut config, "theme-x").is_none());
+
+        let owned = config.repos[0].themes.clone();
+        let theme = Theme::find_mut(&mut config, "theme-y", &repo).unwrap();
+        Repo::rename_installed(tmpdir.path(), theme, &owned).unwrap();
+        assert!(themes_dir.join("theme-y").is_dir());
+        let theme = Theme::find_installed(&mut config, "theme-y").unwrap();
+        assert_eq!(theme.directory, Some(themes_dir.join("theme-y")));
+    }
+
+    #[test]
+    fn test_plan_lock() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let source = tmpdir.path().join("upstream");
+        let (_, first, _) = upstream(&source);
+        let themes_dir = tmpdir.path().join(THEMES_DIR);
+        let mut config = Config::new(Some(tmpdir.path().to_path_buf())).default();
+        config.repos[0]
+            .themes
+            .push(Theme::new("extra", None, Some(themes_dir.join("extra"))));
+        let lock = LockFile {
+            current: None,
+            theme: vec![LockedTheme {
+                name: String::from("forest"),
+                repo: String::from("friends"),
+                repo_url: String::from("https://example.com/known.toml"),
+                repository: source.to_str().unwrap().to_string(),
+                commit: first.to_string(),
+                relative_directory: None,
+            }],
+        };
+        let wanted = Wanted::from(lock);
+        assert!(wanted.keep_unlisted);
+
+        let steps = plan(&mut config, &wanted).unwrap();
+        let [Step::Install(target)] = steps.as_slice() else {
+            panic!("{:?}", steps);
+        };
+        assert_eq!(target.repo_url, "https://example.com/known.toml");
+        install(&mut config, &themes_dir, target).unwrap();
+        let forest = Theme::find_mut(&mut config, "forest", "friends").unwrap();
+        assert_eq!(forest.commit, Some(first.to_string()));
+        assert!(plan(&mut config, &wanted).unwrap().is_empty());
+    }
+}
@@ -1,9 +1,9 @@
-use crate::models::Config;
+use crate::models::{Config, LeftWm, TempThemes};
+use crate::utils::versions::Compatibility;
 use crate::{errors, utils};
 use clap::Parser;
 use colored::Colorize;
 use log::trace;
-use std::fs;
 use url::Url;
 
 #[derive(Parser, Debug)]
@@ -35,6 +35,9 @@ impl Update {
 
         // List themes
         println!("{}", "\nAvailable themes:".bright_blue().bold());
+        if let Some(note) = LeftWm::get().assumed_note() {
+            println!("{}", note.yellow());
+        }
 
         for repo in &mut config.repos {
             for theme in &mut repo.themes {
@@ -46,15 +49,25 @@ impl Update {
                     Some(_) => "-Installed".red().bold(),
                     None => "".white(),
                 };
-                // Only list installable themes
-                if let Ok(true) = utils::versions::check(
+                let notice = match theme.notice() {
+                    Some(notice) => format!(" ({notice})").bright_red(),
+                    None => "".white(),
+                };
+                let compatibility = utils::versions::check(
                     &theme
                         .leftwm_versions
                         .clone()
                         .unwrap_or_else(|| "*".to_string()),
-                ) {
+                );
+                // Only list installable themes
+                if let Ok(Compatibility::Compatible | Compatibility::Unknown) = compatibility {
+                    let unknown = if compatibility.ok() == Some(Compatibility::Unknown) {
+                        " (compatibility unknown)".yellow()
+                    } else {
+                        "".white()
+                    };
                     println!(
-                        "   {}{}/{}: {}{}",
+                        "   {}{}/{}: {}{}{}{}",
                         current,
                         repo.name.bright_magenta().bold(),
                         theme.name.bright_green().bold(),
@@ -62,7 +75,9 @@ impl Update {
                             .description
                             .as_ref()
                             .unwrap_or(&"A LeftWM theme".to_string()),
-                        installed
+                        installed,
+                        unknown,
+                        notice
                     );
                 } else {
                     // Show incompatible themes if requested
@@ -89,7 +104,7 @@ impl Update {
     // Iterates through the repos in the config, fetches themes from the repos
     // and updates the config with the themes. The downloaded themes are
     // compared with any existing themes and updated.
-    fn update_repos(config: &mut Config) -> Result<(), errors::LeftError> {
+    pub(crate) fn update_repos(config: &mut Config) -> Result<(), errors::LeftError> {
         println!("{}", "Fetching themes . . . ".bright_blue().bold());
         let config_dir = config.get_config_dir()?;
         // Attempt to fetch new themes and populate the config with remote
@@ -102,17 +117,11 @@ impl Update {
                 continue;
             }
 
-            let content: String;
             // Check the url scheme to determine how to fetch the themes.
             let repo_url = Url::parse(repo.url.clone().as_str())?;
-            if repo_url.scheme() == "file" {
-                content = fs::read_to_string(repo_url.path())?;
-            } else {
-                content = reqwest::blocking::get(repo_url.as_str())?.text_with_charset("utf-8")?;
-                trace!("{:?}", &content);
-            }
+            let content = utils::fetch::text(repo_url.as_str())?;
             if !content.is_empty() {
-                repo.compare(toml::from_str(&content)?, &config_dir)?;
+                repo.compare(TempThemes::parse(&content)?, &config_dir)?;
             }
         }
 
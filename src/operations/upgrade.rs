@@ -2,7 +2,8 @@
 // Currently, there is no way of knowing whether a theme needs updated. In a later version it would
 // be nice to skip themes that do not need updates.
 use crate::errors;
-use crate::models::Config;
+use crate::models::{Config, TempThemes};
+use crate::utils::overrides;
 use clap::Parser;
 use colored::Colorize;
 use git2::{Oid, Repository};
@@ -46,11 +47,12 @@ impl Upgrade {
                 trace!("{:?}", &resp);
 
                 //compare to old themes
-                repo.compare(toml::from_str(&resp)?, &config_dir)?;
+                repo.compare(TempThemes::parse(&resp)?, &config_dir)?;
             }
             Config::save(config)?;
         }
         // Update themes
+        let config_dir = config.get_config_dir()?;
         println!("{}", "\nUpdating themes:".bright_blue().bold());
         let mut installed = 0;
         for repo in &config.repos {
@@ -75,7 +77,7 @@ impl Upgrade {
                             .unwrap_or(&"A LeftWM theme".to_string())
                     );
                     let git_repo = Repository::open(theme_directory)?;
-                    match fetch_origin_main(&git_repo) {
+                    match fetch_origin_main(&git_repo, theme.branch.as_deref()) {
                         Ok(()) => {
                             //if defined, attempt to checkout the specific index
                             match &theme.commit {
@@ -94,6 +96,16 @@ impl Upgrade {
                             error!("Could not fetch repo.");
                         }
                     }
+                    // Merge the user's overrides over the updated settings.
+                    if theme.current == Some(true)
+                        && overrides::file(&config_dir, &theme.name).exists()
+                    {
+                        let mut path = theme_directory.clone();
+                        if let Some(rel_dir) = theme.relative_directory() {
+                            path.push(rel_dir);
+                        }
+                        overrides::resolve(&config_dir, &theme.name, &path)?;
+                    }
 
                     installed += 1;
                 }
@@ -106,11 +118,15 @@ impl Upgrade {
     }
 }
 
-pub(crate) fn fetch_origin_main(repo: &git2::Repository) -> Result<(), git2::Error> {
+// Fetches and merges the theme's branch from origin; master unless given.
+pub(crate) fn fetch_origin_main(
+    repo: &git2::Repository,
+    branch: Option<&str>,
+) -> Result<(), git2::Error> {
     use crate::utils::merge::{run, Args};
     let args = Args {
         arg_remote: None,
-        arg_branch: None,
+        arg_branch: branch.map(String::from),
     };
     run(&args, repo)
 }
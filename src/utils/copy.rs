@@ -0,0 +1,168 @@
+//! Copying theme directories, e.g. from a template or an installed theme.
+use crate::errors::{friendly_message, Result};
+use colored::Colorize;
+use std::fs;
+use std::io;
+use std::os::unix;
+use std::path::{Path, PathBuf};
+
+/// What a [`tree`] copy did.
+#[derive(Debug, Default)]
+pub struct Summary {
+    pub files: usize,
+    pub directories: usize,
+    pub symlinks: usize,
+    /// Entries that could not be copied, with the reason
+    pub errors: Vec<(PathBuf, io::Error)>,
+}
+
+impl Summary {
+    /// Prints what was copied and every entry that failed.
+    pub fn report(&self) {
+        println!(
+            "{}",
+            format!(
+                "Copied {} file(s), {} directory(ies) and {} symlink(s).",
+                self.files, self.directories, self.symlinks
+            )
+            .bright_blue()
+            .bold()
+        );
+        for (path, err) in &self.errors {
+            println!(
+                "{}",
+                format!("Could not copy {}: {err}", path.display()).bright_red()
+            );
+        }
+    }
+}
+
+/// Recursively copies `source` into `target`, keeping permissions and
+/// symlinks and leaving out `.git`. Entries that fail are recorded in the
+/// summary rather than stopping the copy.
+///
+/// # Errors
+///
+/// Will error if `source` is not a directory or `target` cannot be created.
+pub fn tree(source: &Path, target: &Path) -> Result<Summary> {
+    if !source.is_dir() {
+        return Err(friendly_message(&format!(
+            "{} is not a directory.",
+            source.display()
+        )));
+    }
+    let mut summary = Summary::default();
+    copy_dir(source, target, &mut summary)?;
+    Ok(summary)
+}
+
+fn copy_dir(source: &Path, target: &Path, summary: &mut Summary) -> io::Result<()> {
+    fs::create_dir_all(target)?;
+    for entry in fs::read_dir(source)? {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                summary.errors.push((source.to_path_buf(), err));
+                continue;
+            }
+        };
+        if entry.file_name() == ".git" {
+            continue;
+        }
+        let from = entry.path();
+        let to = target.join(entry.file_name());
+        if let Err(err) = copy_entry(&entry, &from, &to, summary) {
+            summary.errors.push((from, err));
+        }
+    }
+    Ok(())
+}
+
+fn copy_entry(
+    entry: &fs::DirEntry,
+    from: &Path,
+    to: &Path,
+    summary: &mut Summary,
+) -> io::Result<()> {
+    let file_type = entry.file_type()?;
+    if file_type.is_symlink() {
+        unix::fs::symlink(fs::read_link(from)?, to)?;
+        summary.symlinks += 1;
+    } else if file_type.is_dir() {
+        copy_dir(from, to, summary)?;
+        fs::set_permissions(to, fs::metadata(from)?.permissions())?;
+        summary.directories += 1;
+    } else {
+        // Copies the permission bits too.
+        fs::copy(from, to)?;
+        summary.files += 1;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn test_tree() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let source = tmpdir.path().join("source");
+        let target = tmpdir.path().join("target");
+        fs::create_dir_all(source.join("polybar").join("scripts")).unwrap();
+        fs::create_dir_all(source.join(".git")).unwrap();
+        fs::write(source.join(".git").join("HEAD"), "").unwrap();
+        fs::write(source.join("up"), "#!/bin/sh").unwrap();
+        fs::set_permissions(source.join("up"), fs::Permissions::from_mode(0o755)).unwrap();
+        fs::write(
+            source.join("polybar").join("scripts").join("clock.sh"),
+            "date",
+        )
+        .unwrap();
+        unix::fs::symlink("up", source.join("up-link")).unwrap();
+
+        let summary = tree(&source, &target).unwrap();
+        assert_eq!(summary.files, 2);
+        assert_eq!(summary.directories, 2);
+        assert_eq!(summary.symlinks, 1);
+        assert!(summary.errors.is_empty());
+
+        assert!(target
+            .join("polybar")
+            .join("scripts")
+            .join("clock.sh")
+            .exists());
+        assert!(!target.join(".git").exists());
+        let mode = fs::metadata(target.join("up"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o755);
+        assert_eq!(
+            fs::read_link(target.join("up-link")).unwrap(),
+            PathBuf::from("up")
+        );
+    }
+
+    #[test]
+    fn test_tree_records_failures() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let source = tmpdir.path().join("source");
+        let target = tmpdir.path().join("target");
+        fs::create_dir_all(&source).unwrap();
+        fs::create_dir_all(&target).unwrap();
+        fs::write(source.join("theme.ron"), "()").unwrap();
+        fs::write(source.join("up"), "").unwrap();
+        // An existing symlink cannot be replaced.
+        unix::fs::symlink("elsewhere", source.join("link")).unwrap();
+        unix::fs::symlink("elsewhere", target.join("link")).unwrap();
+
+        let summary = tree(&source, &target).unwrap();
+        assert_eq!(summary.files, 2);
+        assert_eq!(summary.errors.len(), 1);
+        assert_eq!(summary.errors[0].0, source.join("link"));
+
+        assert!(tree(&source.join("missing"), &target).is_err());
+    }
+}
@@ -0,0 +1,78 @@
+use colored::Colorize;
+
+/// A line of a line-by-line diff.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Line<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Diffs two texts line by line, using the longest common subsequence.
+#[must_use]
+pub fn lines<'a>(old: &'a str, new: &'a str) -> Vec<Line<'a>> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // lcs[i][j] is the LCS length of old[i..] and new[j..].
+    let mut lcs = vec![vec![0_usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut result = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            result.push(Line::Same(old[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            result.push(Line::Removed(old[i]));
+            i += 1;
+        } else {
+            result.push(Line::Added(new[j]));
+            j += 1;
+        }
+    }
+    result.extend(old[i..].iter().map(|l| Line::Removed(l)));
+    result.extend(new[j..].iter().map(|l| Line::Added(l)));
+    result
+}
+
+/// Prints a colored line-by-line diff of two texts.
+pub fn print(old: &str, new: &str) {
+    for line in lines(old, new) {
+        match line {
+            Line::Same(l) => println!("  {l}"),
+            Line::Removed(l) => println!("{}", format!("- {l}").red()),
+            Line::Added(l) => println!("{}", format!("+ {l}").green()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_lines() {
+        assert_eq!(
+            lines("a\nb\nc\n", "a\nc\nd\n"),
+            vec![
+                Line::Same("a"),
+                Line::Removed("b"),
+                Line::Same("c"),
+                Line::Added("d"),
+            ]
+        );
+        assert_eq!(lines("", "a"), vec![Line::Added("a")]);
+        assert_eq!(lines("a", ""), vec![Line::Removed("a")]);
+    }
+}
@@ -1,11 +1,9 @@
 use crate::errors::Result;
-use xdg::BaseDirectories;
+use crate::models::{Config, THEMES_DIR};
 /// # Errors
 ///
 /// Will error if `BaseDirectory` not set
 /// Will error if unable to create theme leftwm directory
-pub fn theme() -> Result<std::path::PathBuf> {
-    let mut dir = BaseDirectories::with_prefix("leftwm").create_config_directory("")?;
-    dir.push("themes");
-    Ok(dir)
+pub fn theme(config: &Config) -> Result<std::path::PathBuf> {
+    Ok(config.get_config_dir()?.join(THEMES_DIR))
 }
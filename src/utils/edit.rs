@@ -0,0 +1,383 @@
+//! In-place edits of top-level fields in `theme.ron` and `theme.toml`, touching
+//! only the edited value so comments and formatting elsewhere survive.
+use crate::errors::{LeftError, Result};
+
+/// A top-level field of a RON struct and the byte range of its value.
+#[derive(Debug, PartialEq, Eq)]
+struct RonField {
+    name: String,
+    value: (usize, usize),
+}
+
+/// Sets `field` to the RON `literal`, replacing its value or adding the field.
+///
+/// # Errors
+///
+/// Will error if the contents are not a RON struct.
+pub fn ron_set(contents: &str, field: &str, literal: &str) -> Result<String> {
+    let (fields, close) = ron_fields(contents)?;
+    if let Some(existing) = fields.iter().find(|f| f.name == field) {
+        let (start, end) = existing.value;
+        return Ok(format!(
+            "{}{literal}{}",
+            &contents[..start],
+            &contents[end..]
+        ));
+    }
+
+    // Append the field, making sure the previous one ends with a comma.
+    let mut before = contents[..close].to_string();
+    if let Some(last) = fields.last() {
+        let between = skip_ignored(contents.as_bytes(), last.value.1);
+        if contents.as_bytes().get(between) != Some(&b',') {
+            before.insert(last.value.1, ',');
+        }
+    }
+    let trimmed = before.trim_end_matches([' ', '\t']);
+    if trimmed.ends_with('\n') {
+        before.truncate(trimmed.len());
+    } else {
+        before.push('\n');
+    }
+    Ok(format!(
+        "{before}    {field}: {literal},\n{}",
+        &contents[close..]
+    ))
+}
+
+// Finds the top-level fields of a RON struct and the index of its closing paren.
+fn ron_fields(contents: &str) -> Result<(Vec<RonField>, usize)> {
+    let not_a_struct = || LeftError::from("Theme file is not a RON struct");
+    let bytes = contents.as_bytes();
+    let mut i = skip_ignored(bytes, 0);
+    // Extension attributes, e.g. #![enable(implicit_some)]
+    while bytes.get(i) == Some(&b'#') {
+        let close = contents[i..].find(']').ok_or_else(not_a_struct)?;
+        i = skip_ignored(bytes, i + close + 1);
+    }
+    // An optional struct name.
+    while bytes.get(i).is_some_and(|b| is_ident_byte(*b)) {
+        i += 1;
+    }
+    i = skip_ignored(bytes, i);
+    if bytes.get(i) != Some(&b'(') {
+        return Err(not_a_struct());
+    }
+    i += 1;
+
+    let mut fields = Vec::new();
+    loop {
+        i = skip_ignored(bytes, i);
+        match bytes.get(i) {
+            Some(b')') => return Ok((fields, i)),
+            Some(b',') => {
+                i += 1;
+                continue;
+            }
+            None => return Err(not_a_struct()),
+            _ => {}
+        }
+        let name_start = i;
+        if contents[i..].starts_with("r#") {
+            i += 2;
+        }
+        while bytes.get(i).is_some_and(|b| is_ident_byte(*b)) {
+            i += 1;
+        }
+        let name = contents[name_start..i].trim_start_matches("r#").to_string();
+        i = skip_ignored(bytes, i);
+        if name.is_empty() || bytes.get(i) != Some(&b':') {
+            return Err(not_a_struct());
+        }
+        let start = skip_ignored(bytes, i + 1);
+        let end = skip_value(bytes, start).ok_or_else(not_a_struct)?;
+        fields.push(RonField {
+            name,
+            value: (start, end),
+        });
+        i = end;
+    }
+}
+
+// Returns the index just past the value starting at `start`: up to a comma or
+// closing bracket at the same nesting level, trailing whitespace excluded.
+fn skip_value(bytes: &[u8], start: usize) -> Option<usize> {
+    let mut depth = 0_usize;
+    let mut i = start;
+    let mut end = start;
+    while let Some(&b) = bytes.get(i) {
+        match b {
+            b'"' => {
+                i = skip_string(bytes, i)?;
+                end = i;
+                continue;
+            }
+            b'/' if matches!(bytes.get(i + 1), Some(b'/' | b'*')) => {
+                i = skip_ignored(bytes, i);
+                continue;
+            }
+            b'(' | b'[' | b'{' => depth += 1,
+            b',' | b')' | b']' | b'}' if depth == 0 => return Some(end),
+            b')' | b']' | b'}' => depth -= 1,
+            _ => {}
+        }
+        i += 1;
+        if !b.is_ascii_whitespace() {
+            end = i;
+        }
+    }
+    Some(end)
+}
+
+// Returns the index just past the string literal starting at `start`.
+fn skip_string(bytes: &[u8], start: usize) -> Option<usize> {
+    let mut i = start + 1;
+    while let Some(&b) = bytes.get(i) {
+        match b {
+            b'\\' => i += 2,
+            b'"' => return Some(i + 1),
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+// Skips whitespace and comments.
+fn skip_ignored(bytes: &[u8], mut i: usize) -> usize {
+    loop {
+        while bytes.get(i).is_some_and(u8::is_ascii_whitespace) {
+            i += 1;
+        }
+        if bytes.get(i) == Some(&b'/') && bytes.get(i + 1) == Some(&b'/') {
+            while bytes.get(i).is_some_and(|b| *b != b'\n') {
+                i += 1;
+            }
+        } else if bytes.get(i) == Some(&b'/') && bytes.get(i + 1) == Some(&b'*') {
+            i += 2;
+            while i < bytes.len() && !(bytes[i - 1] == b'*' && bytes[i] == b'/') {
+                i += 1;
+            }
+            i += 1;
+        } else {
+            return i;
+        }
+    }
+}
+
+fn is_ident_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_'
+}
+
+/// Sets `field` to the TOML `literal`, replacing its `key = value` line (and any
+/// `[field]` / `[[field]]` tables) or adding it after the last top-level key.
+pub fn toml_set(contents: &str, field: &str, literal: &str) -> String {
+    let lines: Vec<&str> = contents.lines().collect();
+    let new_line = format!("{field} = {literal}");
+    let mut result: Vec<String> = Vec::new();
+    let mut replaced = false;
+    let mut in_top_level = true;
+    let mut in_field_table = false;
+    // Bracket nesting of a multi-line value being passed over.
+    let mut depth = 0;
+    // Index in `result` after the last top-level key, for appending.
+    let mut insert_at = 0;
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        let trimmed = line.trim_start();
+        if depth > 0 {
+            depth += bracket_depth(split_toml_comment(line).0);
+            if !in_field_table {
+                result.push(line.to_string());
+            }
+            i += 1;
+            continue;
+        }
+        if trimmed.starts_with('[') {
+            in_top_level = false;
+            let header = trimmed.trim_start_matches('[').trim_start();
+            in_field_table = header
+                .strip_prefix(field)
+                .is_some_and(|rest| rest.trim_start().starts_with([']', '.']));
+            if in_field_table {
+                i += 1;
+                continue;
+            }
+        } else if in_field_table {
+            depth += bracket_depth(split_toml_comment(line).0);
+            i += 1;
+            continue;
+        }
+
+        if in_top_level && is_toml_key(trimmed, field) {
+            // Replace the value, keeping a trailing comment, and drop any
+            // continuation lines of a multi-line value.
+            let (code, comment) = split_toml_comment(line);
+            let mut value_depth = bracket_depth(code);
+            let mut last_comment = comment;
+            while value_depth > 0 && i + 1 < lines.len() {
+                i += 1;
+                let (code, comment) = split_toml_comment(lines[i]);
+                value_depth += bracket_depth(code);
+                last_comment = comment;
+            }
+            let indent = &line[..line.len() - trimmed.len()];
+            match last_comment {
+                Some(comment) => result.push(format!("{indent}{new_line} {comment}")),
+                None => result.push(format!("{indent}{new_line}")),
+            }
+            replaced = true;
+        } else {
+            depth += bracket_depth(split_toml_comment(line).0);
+            result.push(line.to_string());
+        }
+        if in_top_level && !trimmed.is_empty() && !trimmed.starts_with('#') {
+            insert_at = result.len();
+        }
+        i += 1;
+    }
+    if !replaced {
+        result.insert(insert_at, new_line);
+    }
+    let mut output = result.join("\n");
+    output.push('\n');
+    output
+}
+
+fn is_toml_key(line: &str, field: &str) -> bool {
+    [
+        field.to_string(),
+        format!("\"{field}\""),
+        format!("'{field}'"),
+    ]
+    .iter()
+    .any(|key| {
+        line.strip_prefix(key.as_str())
+            .is_some_and(|rest| rest.trim_start().starts_with('='))
+    })
+}
+
+// Splits a TOML line into its code and its trailing `# comment`, if any.
+fn split_toml_comment(line: &str) -> (&str, Option<&str>) {
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    for (idx, c) in line.char_indices() {
+        match (quote, c) {
+            (Some('"'), '\\') if !escaped => {
+                escaped = true;
+                continue;
+            }
+            (Some(q), c) if c == q && !escaped => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '#') => return (line[..idx].trim_end(), Some(&line[idx..])),
+            _ => {}
+        }
+        escaped = false;
+    }
+    (line, None)
+}
+
+// Net change of bracket nesting on a line of TOML code.
+fn bracket_depth(code: &str) -> i32 {
+    let mut depth = 0;
+    let mut quote: Option<char> = None;
+    for c in code.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '[' | '{') => depth += 1,
+            (None, ']' | '}') => depth -= 1,
+            _ => {}
+        }
+    }
+    depth
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const RON: &str = "#![enable(implicit_some)]
+// My theme
+(
+    border_width: 1, // thin
+    margin: [
+        1, 2, // top and right
+        3, 4,
+    ],
+    gutter: [(side: Top, value: 24, id: None)],
+    focused_border_color: \"#FF,B53A\",
+)
+";
+
+    #[test]
+    fn test_ron_set_replaces_value() {
+        let edited = ron_set(RON, "border_width", "3").unwrap();
+        assert!(edited.contains("    border_width: 3, // thin\n"));
+        assert!(edited.contains("// My theme"));
+
+        let edited = ron_set(RON, "margin", "5").unwrap();
+        assert!(edited.contains("    margin: 5,\n    gutter:"));
+
+        let edited = ron_set(RON, "focused_border_color", "\"#000000\"").unwrap();
+        assert!(edited.contains("focused_border_color: \"#000000\",\n)"));
+    }
+
+    #[test]
+    fn test_ron_set_adds_field() {
+        let edited = ron_set(RON, "background_color", "\"#000000\"").unwrap();
+        assert!(edited.ends_with(
+            "focused_border_color: \"#FF,B53A\",\n    background_color: \"#000000\",\n)\n"
+        ));
+
+        let edited = ron_set("(border_width: 1)", "margin", "5").unwrap();
+        assert_eq!(edited, "(border_width: 1,\n    margin: 5,\n)");
+    }
+
+    #[test]
+    fn test_ron_set_rejects_non_structs() {
+        assert!(ron_set("[1, 2]", "margin", "5").is_err());
+        assert!(ron_set("(border_width: 1", "margin", "5").is_err());
+    }
+
+    const TOML: &str = "# My theme
+border_width = 1 # thin
+margin = [
+    1, 2,
+    3, 4,
+]
+focused_border_color = \"#FFB53A\"
+
+[[gutter]]
+side = \"Top\"
+value = 24
+
+[layout]
+default = \"Monocle\"
+";
+
+    #[test]
+    fn test_toml_set_replaces_value() {
+        let edited = toml_set(TOML, "border_width", "3");
+        assert!(edited.starts_with("# My theme\nborder_width = 3 # thin\nmargin = [\n"));
+
+        let edited = toml_set(TOML, "margin", "5");
+        assert!(edited.contains("border_width = 1 # thin\nmargin = 5\nfocused_border_color"));
+    }
+
+    #[test]
+    fn test_toml_set_adds_field() {
+        let edited = toml_set(TOML, "background_color", "\"#000000\"");
+        assert!(edited.contains(
+            "focused_border_color = \"#FFB53A\"\nbackground_color = \"#000000\"\n\n[[gutter]]"
+        ));
+    }
+
+    #[test]
+    fn test_toml_set_replaces_tables() {
+        let edited = toml_set(TOML, "gutter", "[{ side = \"Bottom\", value = 20 }]");
+        assert!(edited.contains("gutter = [{ side = \"Bottom\", value = 20 }]\n\n[layout]"));
+        assert!(!edited.contains("[[gutter]]"));
+        assert!(edited.ends_with("[layout]\ndefault = \"Monocle\"\n"));
+    }
+}
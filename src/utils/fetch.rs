@@ -0,0 +1,40 @@
+//! Reading theme indexes (`known.toml`) from the web or the local disk.
+use crate::errors::Result;
+use log::trace;
+use std::fs;
+use url::Url;
+
+/// Returns the contents of a theme index. `location` is an `http(s)://` or
+/// `file://` URL, or a path on the local disk.
+///
+/// # Errors
+///
+/// Will error if the URL or file cannot be read.
+pub fn text(location: &str) -> Result<String> {
+    let Ok(url) = Url::parse(location) else {
+        return Ok(fs::read_to_string(location)?);
+    };
+    if url.scheme() == "file" {
+        return Ok(fs::read_to_string(url.path())?);
+    }
+    let content = reqwest::blocking::get(url.as_str())?.text_with_charset("utf-8")?;
+    trace!("{:?}", &content);
+    Ok(content)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_text_from_disk() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let path = tmpdir.path().join("known.toml");
+        fs::write(&path, "theme = []").unwrap();
+
+        assert_eq!(text(path.to_str().unwrap()).unwrap(), "theme = []");
+        let url = Url::from_file_path(&path).unwrap();
+        assert_eq!(text(url.as_str()).unwrap(), "theme = []");
+        assert!(text(tmpdir.path().join("missing").to_str().unwrap()).is_err());
+    }
+}
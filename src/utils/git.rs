@@ -0,0 +1,228 @@
+use git2::build::CheckoutBuilder;
+use git2::{Direction, Oid, Repository, Signature, StatusOptions};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Commits the given files in the repository containing them, on top of HEAD.
+///
+/// # Errors
+///
+/// - See `git2` errors.
+/// - Errors if a file is outside of the repository's working directory.
+pub fn commit_files(
+    repo_dir: &Path,
+    files: &[&Path],
+    message: &str,
+) -> Result<git2::Oid, git2::Error> {
+    let repo = Repository::discover(repo_dir)?;
+    let workdir = repo
+        .workdir()
+        .and_then(|dir| fs::canonicalize(dir).ok())
+        .ok_or_else(|| git2::Error::from_str("Repository has no working directory"))?;
+
+    let mut index = repo.index()?;
+    for file in files {
+        let file = fs::canonicalize(file).map_err(|err| git2::Error::from_str(&err.to_string()))?;
+        let relative = file
+            .strip_prefix(&workdir)
+            .map_err(|_| git2::Error::from_str("File is not inside the repository"))?;
+        index.add_path(relative)?;
+    }
+    index.write()?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+
+    let signature = repo
+        .signature()
+        .or_else(|_| Signature::now("leftwm-theme", "leftwm-theme@localhost"))?;
+    let parent = match repo.head() {
+        Ok(head) => Some(head.peel_to_commit()?),
+        Err(_) => None,
+    };
+    let parents: Vec<&git2::Commit> = parent.iter().collect();
+    repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        message,
+        &tree,
+        &parents,
+    )
+}
+
+/// Returns the commit checked out in the repository at `dir`.
+///
+/// # Errors
+///
+/// - See `git2` errors.
+pub fn head_commit(dir: &Path) -> Result<String, git2::Error> {
+    let repo = Repository::open(dir)?;
+    let commit = repo.head()?.peel_to_commit()?;
+    Ok(commit.id().to_string())
+}
+
+/// Returns the paths, relative to the working directory, with uncommitted
+/// changes; untracked files are only listed if `untracked` is set.
+///
+/// # Errors
+///
+/// - See `git2` errors.
+pub fn changed_paths(repo: &Repository, untracked: bool) -> Result<Vec<PathBuf>, git2::Error> {
+    let mut options = StatusOptions::new();
+    options
+        .include_untracked(untracked)
+        .recurse_untracked_dirs(untracked);
+    let statuses = repo.statuses(Some(&mut options))?;
+    Ok(statuses
+        .iter()
+        .filter_map(|entry| entry.path().map(PathBuf::from))
+        .collect())
+}
+
+/// Checks out `commit`, detaching HEAD. The commit is fetched from `origin`
+/// first if the repository doesn't have it yet.
+///
+/// # Errors
+///
+/// - See `git2` errors.
+/// - Errors if the commit is not a full SHA or cannot be found on `origin`.
+/// - Errors if tracked files have uncommitted changes, which would be lost.
+pub fn checkout_commit(repo: &Repository, commit: &str) -> Result<(), git2::Error> {
+    let oid = Oid::from_str(commit)?;
+    ensure_clean(repo)?;
+    if repo.find_commit(oid).is_err() {
+        let mut remote = repo.find_remote("origin")?;
+        remote.fetch(&[] as &[&str], None, None)?;
+    }
+    repo.set_head_detached(oid)?;
+    repo.checkout_head(Some(CheckoutBuilder::new().force()))
+}
+
+/// Errors if tracked files have uncommitted changes, which a forced checkout
+/// would overwrite.
+///
+/// # Errors
+///
+/// - See `git2` errors.
+pub fn ensure_clean(repo: &Repository) -> Result<(), git2::Error> {
+    if changed_paths(repo, false)?.is_empty() {
+        return Ok(());
+    }
+    let dir = repo.workdir().unwrap_or_else(|| repo.path());
+    Err(git2::Error::from_str(&format!(
+        "{} has uncommitted changes; commit or discard them first",
+        dir.display()
+    )))
+}
+
+/// Returns the commit `branch` points to on `origin`, like `git ls-remote`,
+/// without fetching anything.
+///
+/// # Errors
+///
+/// - See `git2` errors.
+/// - Errors if `origin` has no such branch.
+pub fn branch_tip(repo: &Repository, branch: &str) -> Result<Oid, git2::Error> {
+    let mut remote = repo.find_remote("origin")?;
+    remote.connect(Direction::Fetch)?;
+    let refname = format!("refs/heads/{branch}");
+    let tip = remote
+        .list()?
+        .iter()
+        .find(|head| head.name() == refname)
+        .map(git2::RemoteHead::oid);
+    remote.disconnect()?;
+    tip.ok_or_else(|| git2::Error::from_str(&format!("origin has no branch {branch}")))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_commit_files() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(tmpdir.path()).unwrap();
+        let file = tmpdir.path().join("theme.ron");
+        fs::write(&file, "()").unwrap();
+
+        // Both the initial commit and one on top of it.
+        assert!(commit_files(tmpdir.path(), &[&file], "Add theme.ron").is_ok());
+        fs::write(&file, "(border_width: 1)").unwrap();
+        let oid = commit_files(tmpdir.path(), &[&file], "Update theme.ron").unwrap();
+
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        assert_eq!(head.id(), oid);
+        assert_eq!(head.parent_count(), 1);
+        assert!(head.tree().unwrap().get_name("theme.ron").is_some());
+    }
+
+    #[test]
+    fn test_changed_paths() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(tmpdir.path()).unwrap();
+        let file = tmpdir.path().join("theme.ron");
+        fs::write(&file, "()").unwrap();
+        commit_files(tmpdir.path(), &[&file], "Add theme.ron").unwrap();
+        assert!(changed_paths(&repo, true).unwrap().is_empty());
+
+        fs::create_dir(tmpdir.path().join("extra")).unwrap();
+        fs::write(tmpdir.path().join("extra").join("up"), "").unwrap();
+        assert!(changed_paths(&repo, false).unwrap().is_empty());
+        assert_eq!(
+            changed_paths(&repo, true).unwrap(),
+            vec![PathBuf::from("extra/up")]
+        );
+        fs::write(&file, "(border_width: 1)").unwrap();
+        assert_eq!(
+            changed_paths(&repo, false).unwrap(),
+            vec![PathBuf::from("theme.ron")]
+        );
+    }
+
+    #[test]
+    fn test_checkout_commit() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let upstream = tmpdir.path().join("upstream");
+        Repository::init(&upstream).unwrap();
+        let file = upstream.join("theme.ron");
+        fs::write(&file, "()").unwrap();
+        let first = commit_files(&upstream, &[&file], "First").unwrap();
+
+        let clone = tmpdir.path().join("clone");
+        let repo = Repository::clone(upstream.to_str().unwrap(), &clone).unwrap();
+        fs::write(&file, "(border_width: 1)").unwrap();
+        let second = commit_files(&upstream, &[&file], "Second").unwrap();
+
+        // The second commit has to be fetched first.
+        checkout_commit(&repo, &second.to_string()).unwrap();
+        assert_eq!(head_commit(&clone).unwrap(), second.to_string());
+        checkout_commit(&repo, &first.to_string()).unwrap();
+        assert_eq!(head_commit(&clone).unwrap(), first.to_string());
+        assert_eq!(fs::read_to_string(clone.join("theme.ron")).unwrap(), "()");
+        assert!(checkout_commit(&repo, "main").is_err());
+
+        // Local edits are not overwritten.
+        fs::write(clone.join("theme.ron"), "(border_width: 2)").unwrap();
+        assert!(checkout_commit(&repo, &second.to_string()).is_err());
+        assert_eq!(
+            fs::read_to_string(clone.join("theme.ron")).unwrap(),
+            "(border_width: 2)"
+        );
+        assert_eq!(head_commit(&clone).unwrap(), first.to_string());
+        fs::write(clone.join("theme.ron"), "()").unwrap();
+
+        // The branch tip is read without fetching it.
+        let branch = Repository::open(&upstream)
+            .unwrap()
+            .head()
+            .unwrap()
+            .shorthand()
+            .unwrap()
+            .to_string();
+        fs::write(&file, "(border_width: 3)").unwrap();
+        let third = commit_files(&upstream, &[&file], "Third").unwrap();
+        assert_eq!(branch_tip(&repo, &branch).unwrap(), third);
+        assert!(repo.find_commit(third).is_err());
+        assert!(branch_tip(&repo, "no-such-branch").is_err());
+    }
+}
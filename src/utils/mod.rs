@@ -1,4 +1,12 @@
+pub mod copy;
+pub mod diff;
 pub mod dir;
+pub mod edit;
+pub mod fetch;
+pub mod git;
 pub mod merge;
+pub mod overrides;
+pub mod palette;
 pub mod read;
+pub mod scripts;
 pub mod versions;
@@ -0,0 +1,192 @@
+//! Per-theme user overrides, kept in `<config>/theme-overrides/<name>.ron`
+//! outside the theme's git checkout so they survive `upgrade`.
+//!
+//! When a theme with overrides is applied, its settings and the overrides are
+//! merged into a generated copy of the theme, which `current` then points to.
+//! Every other file of the theme is symlinked into the copy.
+use crate::errors::{friendly_message, Result};
+use crate::models::ThemeFile;
+use log::trace;
+use std::fs;
+use std::io::ErrorKind;
+use std::os::unix;
+use std::path::{Path, PathBuf};
+
+pub const OVERRIDES_DIR: &str = "theme-overrides";
+const GENERATED_DIR: &str = ".generated";
+
+// Settings files of a theme, in the order LeftWM prefers them.
+const THEME_FILES: &[&str] = &["theme.ron", "theme.toml"];
+
+/// Returns the overrides file of a theme, whether or not it exists.
+#[must_use]
+pub fn file(config_dir: &Path, theme_name: &str) -> PathBuf {
+    config_dir
+        .join(OVERRIDES_DIR)
+        .join(format!("{}.ron", theme_name.replace('/', "_")))
+}
+
+/// Returns the directory `current` should point to for a theme installed in
+/// `theme_dir`: the theme itself, or a freshly generated copy with the user's
+/// overrides merged over its settings.
+///
+/// # Errors
+///
+/// Will error if the overrides or the theme's settings cannot be parsed.
+/// Will error if the generated copy cannot be written.
+pub fn resolve(config_dir: &Path, theme_name: &str, theme_dir: &Path) -> Result<PathBuf> {
+    let overrides_path = file(config_dir, theme_name);
+    let generated = generated_dir(config_dir, theme_name);
+    remove_dir(&generated)?;
+    if !overrides_path.exists() {
+        trace!("No overrides for {theme_name}");
+        return Ok(theme_dir.to_path_buf());
+    }
+
+    let overrides = ThemeFile::load(&overrides_path).map_err(|err| {
+        friendly_message(&format!(
+            "Could not parse overrides {}: {err}",
+            overrides_path.display()
+        ))
+    })?;
+    let base = THEME_FILES
+        .iter()
+        .map(|name| theme_dir.join(name))
+        .find(|path| path.is_file());
+    let mut settings = match &base {
+        Some(path) => ThemeFile::load(path)?,
+        None => ThemeFile::default(),
+    };
+    settings.merge(&overrides);
+
+    fs::create_dir_all(&generated)?;
+    for entry in fs::read_dir(theme_dir)? {
+        let entry = entry?;
+        if THEME_FILES.iter().any(|name| entry.file_name() == *name) {
+            continue;
+        }
+        unix::fs::symlink(entry.path(), generated.join(entry.file_name()))?;
+    }
+    // Keep the format of the theme, so older LeftWM versions still read it.
+    let header = format!(
+        "Generated by leftwm-theme from {} and {}; edit the overrides instead.",
+        base.as_deref().unwrap_or(theme_dir).display(),
+        overrides_path.display()
+    );
+    if base.is_some_and(|path| path.ends_with("theme.toml")) {
+        let contents = format!("# {header}\n{}", settings.to_toml_string()?);
+        fs::write(generated.join("theme.toml"), contents)?;
+    } else {
+        let contents = format!("// {header}\n{}\n", settings.to_ron_string()?);
+        fs::write(generated.join("theme.ron"), contents)?;
+    }
+    Ok(generated)
+}
+
+/// Moves the overrides of a theme that was renamed to its new name, and
+/// removes the copy generated under the old name.
+///
+/// # Errors
+///
+/// Will error if the overrides file cannot be moved.
+pub fn rename(config_dir: &Path, old_name: &str, new_name: &str) -> Result<()> {
+    let old = file(config_dir, old_name);
+    if old.exists() {
+        fs::rename(old, file(config_dir, new_name))?;
+    }
+    remove_dir(&generated_dir(config_dir, old_name))
+}
+
+pub(crate) fn generated_dir(config_dir: &Path, theme_name: &str) -> PathBuf {
+    config_dir
+        .join(OVERRIDES_DIR)
+        .join(GENERATED_DIR)
+        .join(theme_name.replace('/', "_"))
+}
+
+fn remove_dir(dir: &Path) -> Result<()> {
+    match fs::remove_dir_all(dir) {
+        Err(err) if err.kind() != ErrorKind::NotFound => Err(err.into()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const THEME_RON: &str = "#![enable(implicit_some)]
+(
+    border_width: 1,
+    focused_border_color: \"#FFFFFF\",
+    bar_height: 22,
+)";
+
+    #[test]
+    fn test_resolve_without_overrides() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let theme_dir = tmpdir.path().join("themes").join("plain");
+        fs::create_dir_all(&theme_dir).unwrap();
+        assert_eq!(
+            resolve(tmpdir.path(), "plain", &theme_dir).unwrap(),
+            theme_dir
+        );
+    }
+
+    #[test]
+    fn test_resolve_merges_overrides() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let theme_dir = tmpdir.path().join("themes").join("plain");
+        fs::create_dir_all(&theme_dir).unwrap();
+        fs::write(theme_dir.join("theme.ron"), THEME_RON).unwrap();
+        fs::write(theme_dir.join("up"), "#!/bin/sh").unwrap();
+        fs::create_dir_all(tmpdir.path().join(OVERRIDES_DIR)).unwrap();
+        fs::write(
+            file(tmpdir.path(), "plain"),
+            "#![enable(implicit_some)]\n(focused_border_color: \"#FF0000\")",
+        )
+        .unwrap();
+
+        let generated = resolve(tmpdir.path(), "plain", &theme_dir).unwrap();
+        assert_ne!(generated, theme_dir);
+        assert_eq!(
+            fs::read_link(generated.join("up")).unwrap(),
+            theme_dir.join("up")
+        );
+        let settings = ThemeFile::load(&generated.join("theme.ron")).unwrap();
+        assert_eq!(settings.border_width, Some(1));
+        assert_eq!(settings.focused_border_color.as_deref(), Some("#FF0000"));
+        assert!(settings.extra.contains_key("bar_height"));
+
+        // Removing the overrides removes the generated copy.
+        fs::remove_file(file(tmpdir.path(), "plain")).unwrap();
+        assert_eq!(
+            resolve(tmpdir.path(), "plain", &theme_dir).unwrap(),
+            theme_dir
+        );
+        assert!(!generated.exists());
+    }
+
+    #[test]
+    fn test_resolve_keeps_toml_themes_in_toml() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let theme_dir = tmpdir.path().join("themes").join("old");
+        fs::create_dir_all(&theme_dir).unwrap();
+        fs::write(
+            theme_dir.join("theme.toml"),
+            "border_width = 1\nmargin = 5\n",
+        )
+        .unwrap();
+        fs::create_dir_all(tmpdir.path().join(OVERRIDES_DIR)).unwrap();
+        fs::write(file(tmpdir.path(), "old"), "(margin: Some(10))").unwrap();
+
+        let generated = resolve(tmpdir.path(), "old", &theme_dir).unwrap();
+        assert!(!generated.join("theme.ron").exists());
+        let settings = ThemeFile::load(&generated.join("theme.toml")).unwrap();
+        assert_eq!(settings.border_width, Some(1));
+        assert_eq!(
+            settings.margin,
+            Some(crate::models::theme_file::CustomMargins::Int(10))
+        );
+    }
+}
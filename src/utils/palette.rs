@@ -0,0 +1,443 @@
+//! Colours used by a theme, and recolouring a theme with a user palette.
+//!
+//! Colours are found as `#rgb`, `#rrggbb` and `#aarrggbb` / `#rrggbbaa` hex
+//! values in the theme's settings file and in common bar configurations.
+use crate::errors::{friendly_message, Result};
+use crate::models::ThemeFile;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Files of a theme that are searched for colours, relative to the theme.
+pub const COLOR_FILES: &[&str] = &[
+    "theme.ron",
+    "theme.toml",
+    // Bars
+    "polybar.config",
+    "polybar.ini",
+    "polybar/config",
+    "polybar/config.ini",
+    "xmobar.hs",
+    "xmobarrc",
+    "lemonbar.sh",
+    "eww/eww.scss",
+    // Notifications and launchers, often styled along with the bar
+    "dunstrc",
+    "rofi.rasi",
+];
+
+/// An RGB colour.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    /// Parses `rgb` or `rrggbb`, with or without a leading `#`.
+    pub fn parse(hex: &str) -> Option<Self> {
+        let hex = hex.trim().trim_start_matches('#');
+        if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        let channel = |s: &str| u8::from_str_radix(s, 16).ok();
+        match hex.len() {
+            3 => {
+                let double = |i: usize| channel(&hex[i..=i].repeat(2));
+                Some(Rgb(double(0)?, double(1)?, double(2)?))
+            }
+            6 => Some(Rgb(
+                channel(&hex[0..2])?,
+                channel(&hex[2..4])?,
+                channel(&hex[4..6])?,
+            )),
+            _ => None,
+        }
+    }
+
+    // Squared "redmean" distance, a cheap approximation of perceived difference.
+    fn distance(self, other: Rgb) -> i64 {
+        let mean_red = (i64::from(self.0) + i64::from(other.0)) >> 1;
+        let dr = i64::from(self.0) - i64::from(other.0);
+        let dg = i64::from(self.1) - i64::from(other.1);
+        let db = i64::from(self.2) - i64::from(other.2);
+        (((512 + mean_red) * dr * dr) >> 8) + 4 * dg * dg + (((767 - mean_red) * db * db) >> 8)
+    }
+}
+
+impl fmt::Display for Rgb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.0, self.1, self.2)
+    }
+}
+
+/// A colour found in a theme file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Occurrence {
+    pub color: Rgb,
+    /// The file, relative to the theme directory
+    pub file: PathBuf,
+    /// 1-based line number
+    pub line: usize,
+    /// The line the colour was found on, trimmed
+    pub text: String,
+}
+
+// A hex colour literal and its byte range in a line.
+struct Literal {
+    start: usize,
+    end: usize,
+    hex: String,
+}
+
+fn hex_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r"#([0-9A-Fa-f]{8}|[0-9A-Fa-f]{6}|[0-9A-Fa-f]{3})\b").expect("valid regex")
+    })
+}
+
+// Settings files use `#rrggbbaa`; bars such as polybar use `#aarrggbb`.
+fn alpha_first(file: &Path) -> bool {
+    !file.starts_with("theme.ron") && !file.starts_with("theme.toml")
+}
+
+fn literals(line: &str) -> impl Iterator<Item = Literal> + '_ {
+    hex_regex().captures_iter(line).map(|caps| {
+        let whole = caps.get(0).expect("match");
+        Literal {
+            start: whole.start(),
+            end: whole.end(),
+            hex: caps[1].to_string(),
+        }
+    })
+}
+
+// The RGB part of a hex literal, dropping the alpha channel.
+fn rgb_of(hex: &str, alpha_first: bool) -> Option<Rgb> {
+    match hex.len() {
+        8 if alpha_first => Rgb::parse(&hex[2..]),
+        8 => Rgb::parse(&hex[..6]),
+        _ => Rgb::parse(hex),
+    }
+}
+
+/// Finds every colour in the theme's settings and bar configuration files.
+///
+/// # Errors
+///
+/// Will error if a file exists but cannot be read.
+pub fn scan(theme_dir: &Path) -> Result<Vec<Occurrence>> {
+    let mut found = Vec::new();
+    for file in COLOR_FILES {
+        let path = theme_dir.join(file);
+        if !path.is_file() {
+            continue;
+        }
+        let contents = fs::read_to_string(&path)?;
+        let alpha_first = alpha_first(Path::new(file));
+        for (idx, line) in contents.lines().enumerate() {
+            for literal in literals(line) {
+                if let Some(color) = rgb_of(&literal.hex, alpha_first) {
+                    found.push(Occurrence {
+                        color,
+                        file: PathBuf::from(file),
+                        line: idx + 1,
+                        text: line.trim().to_string(),
+                    });
+                }
+            }
+        }
+    }
+    Ok(found)
+}
+
+/// A palette supplied by the user, with the colours given roles in a theme.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserPalette {
+    pub background: Rgb,
+    pub foreground: Rgb,
+    /// Used for unfocused borders
+    pub muted: Rgb,
+    /// Used for the focused border
+    pub accent: Rgb,
+    /// Used for floating borders
+    pub secondary: Rgb,
+    /// Every colour of the palette
+    pub colors: Vec<Rgb>,
+}
+
+impl UserPalette {
+    /// Loads a pywal `colors.json` or a base16 scheme (YAML).
+    ///
+    /// # Errors
+    ///
+    /// Will error if the file cannot be read or is neither format.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let palette = if path.extension().is_some_and(|ext| ext == "json") {
+            UserPalette::from_pywal(&contents)
+        } else {
+            UserPalette::from_base16(&contents)
+        };
+        palette.ok_or_else(|| {
+            friendly_message(&format!(
+                "{} is not a base16 scheme or a pywal colors.json",
+                path.display()
+            ))
+        })
+    }
+
+    /// Reads a pywal `colors.json`, with `special` colours and `color0`-`color15`.
+    pub fn from_pywal(contents: &str) -> Option<Self> {
+        let value: serde_json::Value = serde_json::from_str(contents).ok()?;
+        let color = |i: usize| {
+            value["colors"][format!("color{i}")]
+                .as_str()
+                .and_then(Rgb::parse)
+        };
+        let special = |name: &str| value["special"][name].as_str().and_then(Rgb::parse);
+        let colors: Vec<Rgb> = (0..16).filter_map(color).collect();
+        if colors.len() < 9 {
+            return None;
+        }
+        Some(UserPalette {
+            background: special("background").unwrap_or(colors[0]),
+            foreground: special("foreground").unwrap_or(colors[7]),
+            muted: colors[8],
+            accent: colors[4],
+            secondary: colors[5],
+            colors,
+        })
+    }
+
+    /// Reads a base16 scheme, i.e. `base00` to `base0F` keys with hex values.
+    pub fn from_base16(contents: &str) -> Option<Self> {
+        let re = Regex::new(r#"(?m)^\s*base([0-9A-Fa-f]{2})\s*:\s*["']?#?([0-9A-Fa-f]{6})["']?"#)
+            .ok()?;
+        let base: BTreeMap<u8, Rgb> = re
+            .captures_iter(contents)
+            .filter_map(|caps| {
+                Some((
+                    u8::from_str_radix(&caps[1], 16).ok()?,
+                    Rgb::parse(&caps[2])?,
+                ))
+            })
+            .collect();
+        let get = |i: u8| base.get(&i).copied();
+        Some(UserPalette {
+            background: get(0x00)?,
+            foreground: get(0x05)?,
+            muted: get(0x03)?,
+            accent: get(0x0D)?,
+            secondary: get(0x0E)?,
+            colors: base.values().copied().collect(),
+        })
+    }
+
+    /// Maps each colour of a theme to one of this palette. Colours of the
+    /// theme's border and background settings get the matching role; others
+    /// become the nearest palette colour.
+    pub fn mapping(
+        &self,
+        theme_roles: &[(Option<Rgb>, Role)],
+        found: &[Rgb],
+    ) -> BTreeMap<Rgb, Rgb> {
+        let mut mapping = BTreeMap::new();
+        for (color, role) in theme_roles {
+            if let Some(color) = color {
+                mapping.entry(*color).or_insert_with(|| self.role(*role));
+            }
+        }
+        let mut candidates = self.colors.clone();
+        candidates.extend([self.background, self.foreground]);
+        for color in found {
+            mapping.entry(*color).or_insert_with(|| {
+                *candidates
+                    .iter()
+                    .min_by_key(|candidate| color.distance(**candidate))
+                    .unwrap_or(color)
+            });
+        }
+        mapping
+    }
+
+    fn role(&self, role: Role) -> Rgb {
+        match role {
+            Role::Background => self.background,
+            Role::Muted => self.muted,
+            Role::Accent => self.accent,
+            Role::Secondary => self.secondary,
+        }
+    }
+}
+
+/// What a colour is used for in a theme's settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Background,
+    Muted,
+    Accent,
+    Secondary,
+}
+
+/// The colours of a theme's border and background settings, with their roles.
+pub fn roles(settings: &ThemeFile) -> Vec<(Option<Rgb>, Role)> {
+    let color = |value: &Option<String>| {
+        value
+            .as_deref()
+            .and_then(|value| rgb_of(value.trim().trim_start_matches('#'), false))
+    };
+    vec![
+        (color(&settings.background_color), Role::Background),
+        (color(&settings.default_border_color), Role::Muted),
+        (color(&settings.focused_border_color), Role::Accent),
+        (color(&settings.floating_border_color), Role::Secondary),
+    ]
+}
+
+/// Replaces colours in the contents of a theme file, keeping alpha channels
+/// and the case of the hex digits.
+pub fn recolor(contents: &str, file: &Path, mapping: &BTreeMap<Rgb, Rgb>) -> String {
+    let alpha_first = alpha_first(file);
+    let mut output = String::with_capacity(contents.len());
+    for line in contents.split_inclusive('\n') {
+        let mut last = 0;
+        for literal in literals(line) {
+            let Some(new) = rgb_of(&literal.hex, alpha_first).and_then(|old| mapping.get(&old))
+            else {
+                continue;
+            };
+            let rgb = new.to_string()[1..].to_string();
+            let hex = match literal.hex.len() {
+                8 if alpha_first => format!("{}{rgb}", &literal.hex[..2]),
+                8 => format!("{rgb}{}", &literal.hex[6..]),
+                _ => rgb,
+            };
+            let hex = if literal.hex.bytes().any(|b| b.is_ascii_uppercase()) {
+                hex.to_uppercase()
+            } else {
+                hex
+            };
+            output.push_str(&line[last..literal.start]);
+            output.push('#');
+            output.push_str(&hex);
+            last = literal.end;
+        }
+        output.push_str(&line[last..]);
+    }
+    output
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const BASE16: &str = "scheme: \"Test\"
+author: \"Someone\"
+base00: \"181818\"
+base01: \"282828\"
+base02: \"383838\"
+base03: \"585858\"
+base04: \"b8b8b8\"
+base05: \"d8d8d8\"
+base06: \"e8e8e8\"
+base07: \"f8f8f8\"
+base08: \"ab4642\"
+base09: \"dc9656\"
+base0A: \"f7ca88\"
+base0B: \"a1b56c\"
+base0C: \"86c1b9\"
+base0D: \"7cafc2\"
+base0E: \"ba8baf\"
+base0F: \"a16946\"
+";
+
+    #[test]
+    fn test_parse_rgb() {
+        assert_eq!(Rgb::parse("#FFB53A"), Some(Rgb(0xFF, 0xB5, 0x3A)));
+        assert_eq!(Rgb::parse("fff"), Some(Rgb(0xFF, 0xFF, 0xFF)));
+        assert_eq!(Rgb::parse("#12345"), None);
+        assert_eq!(Rgb::parse("#GGGGGG"), None);
+        assert_eq!(Rgb(1, 2, 255).to_string(), "#0102ff");
+    }
+
+    #[test]
+    fn test_scan() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        fs::write(
+            tmpdir.path().join("theme.ron"),
+            "(\n    focused_border_color: \"#FFB53A\",\n    background_color: \"#00000080\",\n)",
+        )
+        .unwrap();
+        fs::write(
+            tmpdir.path().join("polybar.config"),
+            "# Colours\n[colors]\nbackground = #CC222222\nalert = #f00\n",
+        )
+        .unwrap();
+
+        let found = scan(tmpdir.path()).unwrap();
+        let colors: Vec<(Rgb, &str, usize)> = found
+            .iter()
+            .map(|o| (o.color, o.file.to_str().unwrap(), o.line))
+            .collect();
+        assert_eq!(
+            colors,
+            vec![
+                (Rgb(0xFF, 0xB5, 0x3A), "theme.ron", 2),
+                (Rgb(0, 0, 0), "theme.ron", 3),
+                (Rgb(0x22, 0x22, 0x22), "polybar.config", 3),
+                (Rgb(0xFF, 0, 0), "polybar.config", 4),
+            ]
+        );
+        assert_eq!(found[2].text, "background = #CC222222");
+    }
+
+    #[test]
+    fn test_load_base16() {
+        let palette = UserPalette::from_base16(BASE16).unwrap();
+        assert_eq!(palette.background, Rgb(0x18, 0x18, 0x18));
+        assert_eq!(palette.accent, Rgb(0x7c, 0xaf, 0xc2));
+        assert_eq!(palette.colors.len(), 16);
+        assert!(UserPalette::from_base16("scheme: nothing").is_none());
+    }
+
+    #[test]
+    fn test_load_pywal() {
+        let colors: Vec<String> = (0..16)
+            .map(|i| format!("\"color{i}\": \"#0000{i:02x}\""))
+            .collect();
+        let json = format!(
+            "{{\"special\": {{\"background\": \"#101010\", \"foreground\": \"#efefef\"}}, \"colors\": {{{}}}}}",
+            colors.join(", ")
+        );
+        let palette = UserPalette::from_pywal(&json).unwrap();
+        assert_eq!(palette.background, Rgb(0x10, 0x10, 0x10));
+        assert_eq!(palette.accent, Rgb(0, 0, 4));
+        assert_eq!(palette.muted, Rgb(0, 0, 8));
+        assert!(UserPalette::from_pywal("{}").is_none());
+    }
+
+    #[test]
+    fn test_recolor() {
+        let palette = UserPalette::from_base16(BASE16).unwrap();
+        let focused = Rgb(0xFF, 0xB5, 0x3A);
+        let mapping = palette.mapping(
+            &[(Some(focused), Role::Accent), (None, Role::Background)],
+            &[focused, Rgb(0xFE, 0xFE, 0xFE)],
+        );
+        assert_eq!(mapping[&focused], palette.accent);
+        assert_eq!(mapping[&Rgb(0xFE, 0xFE, 0xFE)], Rgb(0xf8, 0xf8, 0xf8));
+
+        let ron = "(focused_border_color: \"#FFB53A\", default_border_color: \"#fefefe80\")\n";
+        assert_eq!(
+            recolor(ron, Path::new("theme.ron"), &mapping),
+            "(focused_border_color: \"#7CAFC2\", default_border_color: \"#f8f8f880\")\n"
+        );
+        let polybar = "foreground = #80fefefe\nunknown = #123456\n";
+        assert_eq!(
+            recolor(polybar, Path::new("polybar.config"), &mapping),
+            "foreground = #80f8f8f8\nunknown = #123456\n"
+        );
+    }
+}
@@ -0,0 +1,231 @@
+//! Inspecting the shell scripts of a theme, e.g. to find the programs they run.
+use std::collections::BTreeSet;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+// Shell keywords and builtins, and tools every system has, which are never
+// theme dependencies.
+const NOT_DEPENDENCIES: &[&str] = &[
+    ".",
+    ":",
+    "[",
+    "[[",
+    "alias",
+    "awk",
+    "basename",
+    "bash",
+    "break",
+    "case",
+    "cat",
+    "cd",
+    "chmod",
+    "command",
+    "continue",
+    "cp",
+    "cut",
+    "date",
+    "declare",
+    "dirname",
+    "disown",
+    "do",
+    "done",
+    "echo",
+    "elif",
+    "else",
+    "env",
+    "esac",
+    "eval",
+    "exec",
+    "exit",
+    "export",
+    "false",
+    "fi",
+    "for",
+    "function",
+    "grep",
+    "head",
+    "if",
+    "in",
+    "kill",
+    "killall",
+    "leftwm",
+    "leftwm-command",
+    "leftwm-state",
+    "let",
+    "ln",
+    "local",
+    "ls",
+    "mkdir",
+    "mv",
+    "nohup",
+    "pgrep",
+    "pidof",
+    "pkill",
+    "printf",
+    "ps",
+    "pwd",
+    "read",
+    "readlink",
+    "return",
+    "rm",
+    "sed",
+    "set",
+    "setsid",
+    "sh",
+    "shift",
+    "sleep",
+    "sort",
+    "source",
+    "sudo",
+    "tail",
+    "tee",
+    "test",
+    "then",
+    "touch",
+    "tr",
+    "trap",
+    "true",
+    "uniq",
+    "unset",
+    "until",
+    "wait",
+    "wc",
+    "while",
+    "xargs",
+];
+
+const BIN_DIRS: &[&str] = &["/bin", "/sbin", "/usr/bin", "/usr/sbin", "/usr/local/bin"];
+
+/// Returns the scripts of a theme: `up`, `down` and every other executable or
+/// `.sh` file below `dir`, leaving out `.git`.
+pub fn find(dir: &Path) -> Vec<PathBuf> {
+    let mut scripts = Vec::new();
+    collect(dir, &mut scripts);
+    scripts.sort();
+    scripts
+}
+
+fn collect(dir: &Path, scripts: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.filter_map(Result::ok) {
+        let path = entry.path();
+        let Ok(metadata) = fs::symlink_metadata(&path) else {
+            continue;
+        };
+        if entry.file_name() == ".git" || metadata.file_type().is_symlink() {
+            continue;
+        }
+        if metadata.is_dir() {
+            collect(&path, scripts);
+        } else if entry.file_name() == "up"
+            || entry.file_name() == "down"
+            || metadata.permissions().mode() & 0o111 != 0
+            || path.extension().is_some_and(|ext| ext == "sh")
+        {
+            scripts.push(path);
+        }
+    }
+}
+
+/// Returns the programs a shell script runs, by looking at the first word of
+/// each command. Shell builtins, common tools, variables and the theme's own
+/// scripts (`$SCRIPTPATH/...`) are left out.
+pub fn called_programs(script: &str) -> BTreeSet<String> {
+    let mut programs = BTreeSet::new();
+    for line in script.lines() {
+        let line = line.trim();
+        if line.starts_with('#') {
+            continue;
+        }
+        let line = line.split(" #").next().unwrap_or_default();
+        let separated = line
+            .replace("$(", ";")
+            .replace('`', ";")
+            .replace("&&", ";")
+            .replace("||", ";")
+            .replace(['|', '&', '(', ')', '{', '}'], ";");
+        for command in separated.split(';') {
+            if let Some(program) = first_program(command) {
+                programs.insert(program);
+            }
+        }
+    }
+    programs
+}
+
+// The program run by a simple command, if it could be a dependency.
+fn first_program(command: &str) -> Option<String> {
+    let word = command
+        .split_whitespace()
+        // Skip variable assignments, e.g. `FOO=bar polybar`.
+        .find(|word| !word.contains('='))?
+        .trim_matches(['"', '\'']);
+    if word.is_empty() || word.contains('$') || word.starts_with('-') || word.starts_with('.') {
+        return None;
+    }
+    // Absolute paths count only if they point into a standard bin directory.
+    let (dir, name) = word.rsplit_once('/').unwrap_or(("", word));
+    if word.contains('/') && !BIN_DIRS.contains(&dir) {
+        return None;
+    }
+    let valid = name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "-_.+".contains(c));
+    if !valid || NOT_DEPENDENCIES.contains(&name) || name.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    Some(name.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const UP: &str = r#"#!/usr/bin/env bash
+export SCRIPTPATH="$( cd "$(dirname "$0")" >/dev/null 2>&1 ; pwd -P )"
+
+# Start the bar (polybar is commented out)
+if [ -f "/tmp/leftwm-theme-down" ]; then
+    /tmp/leftwm-theme-down
+    rm /tmp/leftwm-theme-down
+fi
+leftwm-command "LoadTheme $SCRIPTPATH/theme.ron"
+feh --bg-scale "$SCRIPTPATH/wallpaper.jpg" &
+pgrep picom || picom &> /dev/null &
+$SCRIPTPATH/bar.sh
+WIDTH=1920 /usr/bin/lemonbar -p | sh
+dunst & # notifications
+"#;
+
+    #[test]
+    fn test_called_programs() {
+        let programs: Vec<String> = called_programs(UP).into_iter().collect();
+        assert_eq!(programs, vec!["dunst", "feh", "lemonbar", "picom"]);
+    }
+
+    #[test]
+    fn test_find() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(tmpdir.path().join("scripts")).unwrap();
+        fs::create_dir_all(tmpdir.path().join(".git")).unwrap();
+        fs::write(tmpdir.path().join("up"), "").unwrap();
+        fs::write(tmpdir.path().join("theme.ron"), "").unwrap();
+        fs::write(tmpdir.path().join("scripts").join("clock.sh"), "").unwrap();
+        fs::write(tmpdir.path().join(".git").join("hook.sh"), "").unwrap();
+        let tool = tmpdir.path().join("scripts").join("tool");
+        fs::write(&tool, "").unwrap();
+        fs::set_permissions(&tool, fs::Permissions::from_mode(0o755)).unwrap();
+
+        assert_eq!(
+            find(tmpdir.path()),
+            vec![
+                tmpdir.path().join("scripts").join("clock.sh"),
+                tool,
+                tmpdir.path().join("up"),
+            ]
+        );
+    }
+}
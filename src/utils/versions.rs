@@ -1,13 +1,60 @@
 use crate::errors;
 use crate::models::LeftWm;
+use semver::{Version, VersionReq};
+
+/// Whether a theme can be used with the installed `LeftWM`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compatibility {
+    Compatible,
+    Incompatible,
+    /// The installed `LeftWM` version could not be determined
+    Unknown,
+}
+
 /// # Errors
 ///
-/// Returns error if the `LeftWM` version cannot be obtained.
 /// Returns error if the `LeftWM` version requirements cannot be parsed.
-/// Returns error is the `LeftWM` version cannot be parsed.
-pub fn check(vstring: &str) -> Result<bool, errors::LeftError> {
-    use semver::{Version, VersionReq};
-    let lwmv = LeftWm::get()?;
+pub fn check(vstring: &str) -> Result<Compatibility, errors::LeftError> {
     let requirements = VersionReq::parse(vstring)?;
-    Ok(requirements.matches(&Version::parse(&lwmv.version)?))
+    Ok(match LeftWm::get().version {
+        Some(version) => matches(&requirements, &version),
+        None => Compatibility::Unknown,
+    })
+}
+
+// Pre-releases only match requirements naming the same pre-release, so a
+// pre-release is also accepted wherever its final release would be.
+fn matches(requirements: &VersionReq, version: &Version) -> Compatibility {
+    let release = Version::new(version.major, version.minor, version.patch);
+    if requirements.matches(version) || requirements.matches(&release) {
+        Compatibility::Compatible
+    } else {
+        Compatibility::Incompatible
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_matches() {
+        let req = VersionReq::parse(">=0.4.0").unwrap();
+        assert_eq!(
+            matches(&req, &Version::new(0, 5, 1)),
+            Compatibility::Compatible
+        );
+        assert_eq!(
+            matches(&req, &Version::new(0, 3, 0)),
+            Compatibility::Incompatible
+        );
+        assert_eq!(
+            matches(&req, &Version::parse("0.5.0-rc.1").unwrap()),
+            Compatibility::Compatible
+        );
+        assert_eq!(
+            matches(&req, &Version::parse("0.5.1+12.gdeadbee").unwrap()),
+            Compatibility::Compatible
+        );
+    }
 }